@@ -1,13 +1,58 @@
-use std::alloc::{dealloc, Layout};
+use std::alloc::{alloc_zeroed, dealloc, Layout};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use crate::error::Live2DAllocationError;
+
+// 解放するアドレスを持つのでClone不可
+#[derive(Debug, PartialEq, Eq)]
 pub struct Live2DAddress {
     pub(crate) ptr: *mut u8,
     pub(crate) layout: Layout,
 }
 
+impl Live2DAddress {
+    /// アラインメントを指定してゼロ初期化した領域を確保する
+    /// 大きさ0では確保できないので最低1byte確保する
+    /// 確保できない場合はabortせずにエラーを返す
+    pub(crate) fn allocate_zeroed(
+        size: usize,
+        align: usize,
+    ) -> Result<Self, Live2DAllocationError> {
+        let layout = Layout::from_size_align(size.max(1), align)?;
+        let ptr = unsafe { alloc_zeroed(layout) };
+        if ptr.is_null() {
+            return Err(Live2DAllocationError::OutOfMemory {
+                size: layout.size(),
+                align: layout.align(),
+            });
+        }
+
+        Ok(Live2DAddress { ptr, layout })
+    }
+}
+
 impl Drop for Live2DAddress {
     fn drop(&mut self) {
         unsafe { dealloc(self.ptr, self.layout) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_zeroed_test() {
+        let address = Live2DAddress::allocate_zeroed(0, 64).unwrap();
+        assert_eq!(address.ptr as usize % 64, 0);
+
+        assert!(matches!(
+            Live2DAddress::allocate_zeroed(16, 3),
+            Err(Live2DAllocationError::Layout(_))
+        ));
+        // 確保できない大きさでもabortしない
+        assert!(matches!(
+            Live2DAddress::allocate_zeroed(isize::MAX as usize - 64, 64),
+            Err(Live2DAllocationError::OutOfMemory { .. })
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::error::Live2DSegmentError;
 use crate::model_resource::Live2DModelResource;
 use crate::motion_json;
 
@@ -18,6 +19,16 @@ pub enum AnimationCurveType {
 }
 
 impl AnimationCurveType {
+    #[inline]
+    pub fn first_point(&self) -> AnimationPoint {
+        match self {
+            AnimationCurveType::Linear(f, _) => *f,
+            AnimationCurveType::Bezier(f, _, _, _) => *f,
+            AnimationCurveType::Stepped(f, _) => *f,
+            AnimationCurveType::InverseStepped(f, _) => *f,
+        }
+    }
+
     #[inline]
     pub fn last_point(&self) -> AnimationPoint {
         match self {
@@ -59,6 +70,12 @@ pub struct AnimationCurve {
     pub segments: Vec<AnimationCurveType>,
     /// どこまで再生したか
     pub evaluated_index: usize,
+    /// カーブ個別のフェードイン時間 Noneならモーションの値を使う
+    pub fade_in_time: Option<f32>,
+    /// カーブ個別のフェードアウト時間 Noneならモーションの値を使う
+    pub fade_out_time: Option<f32>,
+    /// bindで取得したパラメータ・パーツの番号
+    pub target_index: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -69,12 +86,79 @@ pub struct Animation {
     pub is_loop: bool,
     /// カーブの個数
     pub curve_count: usize,
+    /// フェードインにかかる時間[秒]
+    pub fade_in_time: f32,
+    /// フェードアウトにかかる時間[秒]
+    pub fade_out_time: f32,
+    /// EyeBlinkカーブを適用するパラメータ
+    pub eye_blink_parameter_ids: Vec<String>,
+    /// LipSyncカーブを適用するパラメータ
+    pub lip_sync_parameter_ids: Vec<String>,
 
     pub curves: HashMap<String, AnimationCurve>,
 }
 
+/// まばたきを表すModelカーブのid
+pub const EYE_BLINK_CURVE_ID: &str = "EyeBlink";
+/// 口パクを表すModelカーブのid
+pub const LIP_SYNC_CURVE_ID: &str = "LipSync";
+
+/// フェード時間の指定がない場合の値[秒]
+pub const DEFAULT_FADE_TIME: f32 = 1.0;
+
+/// フェードの重みを計算するための時間
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationFade {
+    /// フェードイン開始からの経過時間
+    pub fade_in_elapsed: f32,
+    /// フェードアウト終了までの残り時間
+    /// Noneならフェードアウトしない
+    pub fade_out_remaining: Option<f32>,
+}
+
+impl AnimationFade {
+    /// フェードなしで完全に適用する
+    pub const FULL: AnimationFade = AnimationFade {
+        fade_in_elapsed: f32::INFINITY,
+        fade_out_remaining: None,
+    };
+
+    /// フェードイン・フェードアウトの時間から重みを計算する
+    pub fn weight(&self, fade_in_time: f32, fade_out_time: f32) -> f32 {
+        let fade_in = if fade_in_time <= 0.0 {
+            1.0
+        } else {
+            easing_sine(self.fade_in_elapsed / fade_in_time)
+        };
+
+        let fade_out = match self.fade_out_remaining {
+            Some(remaining) if fade_out_time > 0.0 => easing_sine(remaining / fade_out_time),
+            _ => 1.0,
+        };
+
+        fade_in * fade_out
+    }
+}
+
+/// 0から1の値をサインカーブで補間する
+pub fn easing_sine(value: f32) -> f32 {
+    if value < 0.0 {
+        0.0
+    } else if value > 1.0 {
+        1.0
+    } else {
+        0.5 - 0.5 * (value * std::f32::consts::PI).cos()
+    }
+}
+
+/// 負の値は指定なしとして扱う
+fn fade_time(value: Option<f32>) -> Option<f32> {
+    value.filter(|value| *value >= 0.0)
+}
+
 impl Animation {
-    pub fn new(json: &motion_json::MotionJson) -> Animation {
+    /// Segmentsが壊れているカーブがあればエラーを返す
+    pub fn new(json: &motion_json::MotionJson) -> Result<Animation, Live2DSegmentError> {
         let duration = json.Meta.Duration;
         let curve_count = json.Meta.CurveCount;
         let is_loop = json.Meta.Loop;
@@ -85,94 +169,165 @@ impl Animation {
             let segments = match &*curve.Target {
                 "Parameter" => AnimationCurve {
                     curve_type: AnimationType::ParameterAnimationCurve,
-                    segments: parse_segments(&curve.Segments),
+                    segments: parse_segments(&curve.Segments)?,
                     evaluated_index: 0,
+                    fade_in_time: fade_time(curve.FadeInTime),
+                    fade_out_time: fade_time(curve.FadeOutTime),
+                    target_index: None,
                 },
                 "PartOpacity" => AnimationCurve {
                     curve_type: AnimationType::PartOpacityAnimationCurve,
-                    segments: parse_segments(&curve.Segments),
+                    segments: parse_segments(&curve.Segments)?,
+                    evaluated_index: 0,
+                    fade_in_time: fade_time(curve.FadeInTime),
+                    fade_out_time: fade_time(curve.FadeOutTime),
+                    target_index: None,
+                },
+                "Model" => AnimationCurve {
+                    curve_type: AnimationType::ModelAnimationCurve,
+                    segments: parse_segments(&curve.Segments)?,
                     evaluated_index: 0,
+                    fade_in_time: fade_time(curve.FadeInTime),
+                    fade_out_time: fade_time(curve.FadeOutTime),
+                    target_index: None,
                 },
-                _ => panic!(),
+                // 新しいエディタで追加された種類は読み飛ばす
+                _ => continue,
             };
 
             curves.insert(id, segments);
         }
 
-        Animation {
+        Ok(Animation {
             duration,
             is_loop,
             curve_count,
+            fade_in_time: fade_time(json.Meta.FadeInTime).unwrap_or(DEFAULT_FADE_TIME),
+            fade_out_time: fade_time(json.Meta.FadeOutTime).unwrap_or(DEFAULT_FADE_TIME),
+            eye_blink_parameter_ids: vec![],
+            lip_sync_parameter_ids: vec![],
             curves,
-        }
+        })
     }
 
-    /// TODO
-    pub fn add(json: &motion_json::MotionJson) {
-        let _duration = json.Meta.Duration;
-        let _curve_count = json.Meta.CurveCount;
-        let _is_loop = json.Meta.Loop;
-        let mut curves: HashMap<String, AnimationCurve> = HashMap::new();
-
-        for curve in json.Curves.iter() {
-            let id = curve.Id.clone();
-            let segments = match &*curve.Target {
-                "Parameter" => AnimationCurve {
-                    curve_type: AnimationType::ParameterAnimationCurve,
-                    segments: parse_segments(&curve.Segments),
-                    evaluated_index: 0,
-                },
-                "PartOpacity" => AnimationCurve {
-                    curve_type: AnimationType::PartOpacityAnimationCurve,
-                    segments: parse_segments(&curve.Segments),
-                    evaluated_index: 0,
-                },
-                _ => panic!(),
-            };
+    /// ある時間のアニメーションをmodel, parametor, opacityをそれぞれ実行する
+    pub fn evaluate_animation(&mut self, model: &mut Live2DModelResource, time: f32) {
+        self.evaluate_animation_with_fade(model, time, AnimationFade::FULL)
+    }
 
-            curves.insert(id, segments);
-        }
+    /// ある時間のアニメーションを現在のパラメータにフェードの重みで混ぜる
+    pub fn evaluate_animation_with_fade(
+        &mut self,
+        model: &mut Live2DModelResource,
+        time: f32,
+        fade: AnimationFade,
+    ) {
+        let fade_weight = fade.weight(self.fade_in_time, self.fade_out_time);
 
-        // Animation {
-        //     duration,
-        //     is_loop,
-        //     curve_count,
-        //     curves,
-        // }
-    }
+        // Modelカーブは他のカーブに影響するので先に計算する
+        let eye_blink_value = self.evaluate_model_curve(EYE_BLINK_CURVE_ID, time);
+        let lip_sync_value = self.evaluate_model_curve(LIP_SYNC_CURVE_ID, time);
 
-    /// ある時間のアニメーションをmodel, parametor, opacityをそれぞれ実行する
-    pub fn evaluate_animation(&mut self, model: &Live2DModelResource, time: f32) {
         // dbg!(&animation.curves);
         for (id, curve) in self.curves.iter_mut() {
             let mut value = curve.evaluate_curve(time);
 
             match curve.curve_type {
-                AnimationType::ModelAnimationCurve => todo!(),
+                // 他のカーブと合わせて適用する
+                AnimationType::ModelAnimationCurve => {}
                 AnimationType::ParameterAnimationCurve => {
-                    let target = model
-                        .iter_mut_parameters()
-                        .find(|part| part.id() == id)
-                        .expect("not find parameter");
-                    if *target.maximum_value < value {
-                        value = *target.maximum_value;
+                    let index = match curve.target_index.or_else(|| model.parameter_index(id)) {
+                        Some(index) => index,
+                        None => continue,
+                    };
+                    let current = model.csm_get_parameter_values()[index];
+
+                    if let Some(eye_blink_value) = eye_blink_value {
+                        if self.eye_blink_parameter_ids.contains(id) {
+                            value *= eye_blink_value;
+                        }
+                    }
+                    if let Some(lip_sync_value) = lip_sync_value {
+                        if self.lip_sync_parameter_ids.contains(id) {
+                            value += lip_sync_value;
+                        }
+                    }
+
+                    // カーブ個別のフェード時間があればそちらを使う
+                    let weight = if curve.fade_in_time.is_some() || curve.fade_out_time.is_some() {
+                        fade.weight(
+                            curve.fade_in_time.unwrap_or(self.fade_in_time),
+                            curve.fade_out_time.unwrap_or(self.fade_out_time),
+                        )
+                    } else {
+                        fade_weight
+                    };
+                    value = current + (value - current) * weight;
+
+                    let maximum_value = model.csm_get_parameter_maximum_values()[index];
+                    let minimum_value = model.csm_get_parameter_minimum_values()[index];
+                    if maximum_value < value {
+                        value = maximum_value;
                     }
-                    if *target.minimum_value > value {
-                        value = *target.minimum_value;
+                    if minimum_value > value {
+                        value = minimum_value;
                     }
-                    *target.value = value;
+                    model.csm_get_mut_parameter_values()[index] = value;
                 }
                 AnimationType::PartOpacityAnimationCurve => {
-                    let target = model
-                        .iter_mut_parts()
-                        .find(|part| part.id() == id)
-                        .expect("not find part");
-                    *target.opacitiy = value;
+                    // パーツの不透明度はフェードさせない
+                    if let Some(index) = curve.target_index.or_else(|| model.part_index(id)) {
+                        model.csm_get_mut_part_opacities()[index] = value;
+                    }
+                }
+            }
+        }
+
+        // パラメータのカーブがないまばたき・口パクのパラメータはModelカーブの値にする
+        for (ids, model_value) in [
+            (&self.eye_blink_parameter_ids, eye_blink_value),
+            (&self.lip_sync_parameter_ids, lip_sync_value),
+        ] {
+            let model_value = match model_value {
+                Some(model_value) => model_value,
+                None => continue,
+            };
+
+            for id in ids.iter() {
+                if self.curves.contains_key(id) {
+                    continue;
+                }
+
+                if let Some(index) = model.parameter_index(id) {
+                    let value = &mut model.csm_get_mut_parameter_values()[index];
+                    *value = *value + (model_value - *value) * fade_weight;
                 }
             }
         }
     }
 
+    /// モデルのパラメータ・パーツの番号をカーブに結びつける
+    /// モデルに存在しないidのカーブは適用されなくなる
+    pub fn bind(&mut self, model: &Live2DModelResource) {
+        for (id, curve) in self.curves.iter_mut() {
+            curve.target_index = match curve.curve_type {
+                AnimationType::ModelAnimationCurve => None,
+                AnimationType::ParameterAnimationCurve => model.parameter_index(id),
+                AnimationType::PartOpacityAnimationCurve => model.part_index(id),
+            };
+        }
+    }
+
+    /// idのModelカーブがあれば評価する
+    fn evaluate_model_curve(&mut self, id: &str, time: f32) -> Option<f32> {
+        match self.curves.get_mut(id) {
+            Some(curve) if curve.curve_type == AnimationType::ModelAnimationCurve => {
+                Some(curve.evaluate_curve(time))
+            }
+            _ => None,
+        }
+    }
+
     pub fn reset_evaluate_indeies(&mut self) {
         self.curves
             .iter_mut()
@@ -180,80 +335,92 @@ impl Animation {
     }
 }
 
-/// これなんとかしたい
-pub fn parse_segments(segments_vec: &Vec<f32>) -> Vec<AnimationCurveType> {
-    let mut ret = vec![];
+/// motion3.jsonのSegmentsを区間ごとに分ける
+/// 最初の点の後に種類と点が続く 0: Linear 1: Bezier 2: Stepped 3: InverseStepped
+pub fn parse_segments(segments: &[f32]) -> Result<Vec<AnimationCurveType>, Live2DSegmentError> {
+    let point = |index: usize| match (segments.get(index), segments.get(index + 1)) {
+        (Some(time), Some(value)) => Ok(AnimationPoint {
+            time: *time,
+            value: *value,
+        }),
+        _ => Err(Live2DSegmentError::Truncated { index }),
+    };
 
-    let mut index = 2;
+    let mut ret = vec![];
     // 最初の点はどのcurve typeも固定
-    let mut last_point = AnimationPoint {
-        time: segments_vec[0],
-        value: segments_vec[1],
-    };
+    let mut last_point = point(0)?;
 
-    loop {
-        if segments_vec.get(index).is_none() {
-            break;
-        }
+    // 点が1つだけのカーブはその値のまま動かない
+    if segments.len() == 2 {
+        ret.push(AnimationCurveType::Linear(last_point, last_point));
+        return Ok(ret);
+    }
 
-        match segments_vec[index] {
-            1.0 => {
-                ret.push(AnimationCurveType::Bezier(
-                    last_point,
-                    AnimationPoint {
-                        time: segments_vec[index + 1],
-                        value: segments_vec[index + 2],
-                    },
-                    AnimationPoint {
-                        time: segments_vec[index + 3],
-                        value: segments_vec[index + 4],
-                    },
-                    AnimationPoint {
-                        time: segments_vec[index + 5],
-                        value: segments_vec[index + 6],
-                    },
-                ));
-
-                last_point = AnimationPoint {
-                    time: segments_vec[index + 5],
-                    value: segments_vec[index + 6],
-                };
-                index += 7
-            }
-            0.0 => {
-                ret.push(AnimationCurveType::Linear(
-                    last_point,
-                    AnimationPoint {
-                        time: segments_vec[index + 1],
-                        value: segments_vec[index + 2],
-                    },
-                ));
-
-                last_point = AnimationPoint {
-                    time: segments_vec[index + 1],
-                    value: segments_vec[index + 2],
-                };
-                index += 3
+    let mut index = 2;
+    while let Some(segment_type) = segments.get(index) {
+        let segment = match *segment_type {
+            0.0 => AnimationCurveType::Linear(last_point, point(index + 1)?),
+            1.0 => AnimationCurveType::Bezier(
+                last_point,
+                point(index + 1)?,
+                point(index + 3)?,
+                point(index + 5)?,
+            ),
+            2.0 => AnimationCurveType::Stepped(last_point, point(index + 1)?),
+            3.0 => AnimationCurveType::InverseStepped(last_point, point(index + 1)?),
+            segment_type => {
+                return Err(Live2DSegmentError::UnknownType {
+                    index,
+                    segment_type,
+                })
             }
-            _ => panic!(),
         };
+
+        index += if let AnimationCurveType::Bezier(..) = segment {
+            7
+        } else {
+            3
+        };
+        last_point = segment.last_point();
+        ret.push(segment);
     }
 
-    ret
+    Ok(ret)
 }
 
 impl AnimationCurve {
     pub fn evaluate_curve(&mut self, time: f32) -> f32 {
+        let (first, last) = match (self.segments.first(), self.segments.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        let first_point = first.first_point();
+        // NaNはどの区間にも入らないので最初の値にする
+        if time.is_nan() || time <= first_point.time {
+            return first_point.value;
+        }
+        if time >= last.last_point().time {
+            return last.last_point().value;
+        }
+
+        // ループなどで時間が戻った場合は最初から探す
+        if self
+            .segments
+            .get(self.evaluated_index)
+            .map(|segment| time < segment.first_point().time)
+            .unwrap_or(true)
+        {
+            self.evaluated_index = 0;
+        }
+
         let (evaluate_index, target_segment) = self
             .segments
             .iter()
-            .skip(self.evaluated_index)
             .enumerate()
-            .find(|(_, segment)| match segment {
-                AnimationCurveType::Linear(p0, p1) => time >= p0.time && time <= p1.time,
-                AnimationCurveType::Bezier(p0, _, _, p3) => time >= p0.time && time <= p3.time,
-                AnimationCurveType::Stepped(p0, p1) => time >= p0.time && time <= p1.time,
-                AnimationCurveType::InverseStepped(p0, p1) => time >= p0.time && time <= p1.time,
+            .skip(self.evaluated_index)
+            .find(|(_, segment)| {
+                time >= segment.first_point().time && time <= segment.last_point().time
             })
             .expect("not find segment");
 
@@ -403,6 +570,19 @@ impl AnimationCurveType {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn parameter_curve(segments: &[f32]) -> AnimationCurve {
+        AnimationCurve {
+            curve_type: AnimationType::ParameterAnimationCurve,
+            segments: parse_segments(segments).unwrap(),
+            evaluated_index: 0,
+            fade_in_time: None,
+            fade_out_time: None,
+            target_index: None,
+        }
+    }
+
     #[test]
     fn lerp_points_test() {
         use super::*;
@@ -464,4 +644,121 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn fade_weight_test() {
+        use super::*;
+
+        assert_eq!(AnimationFade::FULL.weight(1.0, 1.0), 1.0);
+
+        let fade = AnimationFade {
+            fade_in_elapsed: 0.5,
+            fade_out_remaining: None,
+        };
+        assert!((fade.weight(1.0, 1.0) - 0.5).abs() < 1e-6);
+        assert_eq!(fade.weight(0.0, 1.0), 1.0);
+
+        let fade = AnimationFade {
+            fade_in_elapsed: 2.0,
+            fade_out_remaining: Some(0.0),
+        };
+        assert_eq!(fade.weight(1.0, 1.0), 0.0);
+        assert_eq!(fade.weight(1.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn evaluate_curve_out_of_range_test() {
+        let mut curve = parameter_curve(&[0.0, 0.0, 0.0, 1.0, 10.0, 0.0, 2.0, 20.0]);
+
+        assert_eq!(curve.evaluate_curve(-1.0), 0.0);
+        assert_eq!(curve.evaluate_curve(1.5), 15.0);
+        assert_eq!(curve.evaluate_curve(0.5), 5.0);
+        assert_eq!(curve.evaluate_curve(3.0), 20.0);
+    }
+
+    #[test]
+    fn evaluate_curve_single_point_test() {
+        let mut curve = parameter_curve(&[0.0, 3.0]);
+        assert_eq!(curve.evaluate_curve(-1.0), 3.0);
+        assert_eq!(curve.evaluate_curve(0.0), 3.0);
+        assert_eq!(curve.evaluate_curve(1.0), 3.0);
+
+        curve.segments.clear();
+        assert_eq!(curve.evaluate_curve(1.0), 0.0);
+    }
+
+    #[test]
+    fn parse_segments_test() {
+        let point = |time, value| AnimationPoint { time, value };
+
+        // Stepped, InverseStepped
+        let segments = parse_segments(&[0.0, 1.0, 2.0, 1.0, 5.0, 3.0, 2.0, 10.0]).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                AnimationCurveType::Stepped(point(0.0, 1.0), point(1.0, 5.0)),
+                AnimationCurveType::InverseStepped(point(1.0, 5.0), point(2.0, 10.0)),
+            ]
+        );
+        let mut curve = parameter_curve(&[0.0, 1.0, 2.0, 1.0, 5.0, 3.0, 2.0, 10.0]);
+        assert_eq!(curve.evaluate_curve(0.5), 1.0);
+        assert_eq!(curve.evaluate_curve(1.5), 10.0);
+
+        // 途中で切れているカーブと知らない種類
+        assert_eq!(
+            parse_segments(&[0.0, 0.0, 1.0, 0.5, 1.0, 0.5]),
+            Err(Live2DSegmentError::Truncated { index: 5 })
+        );
+        assert_eq!(
+            parse_segments(&[0.0, 0.0, 0.0, 1.0]),
+            Err(Live2DSegmentError::Truncated { index: 3 })
+        );
+        assert_eq!(
+            parse_segments(&[]),
+            Err(Live2DSegmentError::Truncated { index: 0 })
+        );
+        assert_eq!(
+            parse_segments(&[0.0, 0.0, 4.0, 1.0, 1.0]),
+            Err(Live2DSegmentError::UnknownType {
+                index: 2,
+                segment_type: 4.0
+            })
+        );
+    }
+
+    #[test]
+    fn evaluate_curve_nan_test() {
+        let mut curve = parameter_curve(&[0.0, 0.0, 0.0, 1.0, 10.0, 0.0, 2.0, 20.0]);
+        assert_eq!(curve.evaluate_curve(f32::NAN), 0.0);
+        assert_eq!(curve.evaluate_curve(1.5), 15.0);
+    }
+
+    #[test]
+    fn unknown_target_test() {
+        let json: motion_json::MotionJson = serde_json::from_str(
+            r#"{
+                "Version": 3,
+                "Meta": {
+                    "Duration": 1.0,
+                    "Fps": 30.0,
+                    "Loop": true,
+                    "AreBeziersRestricted": true,
+                    "CurveCount": 2,
+                    "TotalSegmentCount": 2,
+                    "TotalPointCount": 4,
+                    "UserDataCount": 0,
+                    "TotalUserDataSize": 0
+                },
+                "Curves": [
+                    {"Target": "Parameter", "Id": "ParamAngleX", "Segments": [0, 0, 0, 1, 1]},
+                    {"Target": "Unknown", "Id": "Something", "Segments": [0, 0, 0, 1, 1]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let animation = Animation::new(&json).unwrap();
+        assert_eq!(animation.curves.len(), 1);
+        assert!(animation.curves.contains_key("ParamAngleX"));
+    }
 }
@@ -0,0 +1,284 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+use zip::ZipArchive;
+
+const MODEL_JSON_EXTENSION: &str = ".model3.json";
+
+/// 相対パスを指定してファイルの中身を読み込む
+/// model3.jsonから参照されるファイルはすべてここから読み込む
+pub trait AssetSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+}
+
+/// ディレクトリからファイルを読み込む
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileSource {
+    root: PathBuf,
+}
+
+impl FileSource {
+    pub fn new<P>(root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        FileSource {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AssetSource for FileSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(self.root.join(path))
+    }
+}
+
+/// メモリ上のファイルから読み込む
+/// include_bytes!で埋め込んだモデルなどに使う
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemorySource {
+    /// normalize_pathしたパスとファイルの中身
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ファイルを追加する 同じパスのファイルは置き換える
+    pub fn insert<P, B>(&mut self, path: P, bytes: B)
+    where
+        P: AsRef<Path>,
+        B: Into<Vec<u8>>,
+    {
+        self.files
+            .insert(normalize_path(path.as_ref()), bytes.into());
+    }
+
+    pub fn with<P, B>(mut self, path: P, bytes: B) -> Self
+    where
+        P: AsRef<Path>,
+        B: Into<Vec<u8>>,
+    {
+        self.insert(path, bytes);
+        self
+    }
+
+    pub fn contains<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        self.files.contains_key(&normalize_path(path.as_ref()))
+    }
+}
+
+impl AssetSource for MemorySource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&normalize_path(path))
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+/// zipファイルの中のディレクトリから読み込む
+#[derive(Debug)]
+pub struct ZipSource<R> {
+    /// 読み込むたびにシークするのでRefCellに入れる
+    archive: RefCell<ZipArchive<R>>,
+    /// アーカイブ内のディレクトリ 空の場合はアーカイブの最上位
+    root: String,
+}
+
+impl<R> ZipSource<R>
+where
+    R: Read + Seek,
+{
+    /// アーカイブの最上位から読み込む
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::with_root(reader, "")
+    }
+
+    /// アーカイブ内のrootディレクトリから読み込む
+    pub fn with_root<P>(reader: R, root: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(ZipSource {
+            archive: RefCell::new(ZipArchive::new(reader)?),
+            root: normalize_path(root.as_ref()),
+        })
+    }
+
+    #[inline]
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// アーカイブに含まれるファイルの名前
+    pub fn file_names(&self) -> Vec<String> {
+        self.archive
+            .borrow()
+            .file_names()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// root以下にあるmodel3.jsonのrootからのパス
+    /// 複数ある場合は最も浅い階層にあるものを選ぶ
+    pub fn find_model_json(&self) -> Option<String> {
+        self.file_names()
+            .into_iter()
+            .filter_map(|name| self.relative_name(&name).map(|name| name.to_string()))
+            .filter(|name| name.ends_with(MODEL_JSON_EXTENSION))
+            .min_by(|a, b| {
+                let depth = |name: &str| name.matches('/').count();
+                depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
+            })
+    }
+
+    /// アーカイブ内の名前をrootからのパスにする root以下になければNone
+    fn relative_name<'a>(&self, name: &'a str) -> Option<&'a str> {
+        if self.root.is_empty() {
+            return Some(name);
+        }
+
+        name.strip_prefix(self.root.as_str())
+            .and_then(|name| name.strip_prefix('/'))
+    }
+}
+
+/// zipのヘッダーにある展開後の大きさから先に確保する上限
+const ZIP_SIZE_HINT_LIMIT: u64 = 64 * 1024 * 1024;
+
+impl<R> AssetSource for ZipSource<R>
+where
+    R: Read + Seek,
+{
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let name = normalize_path(&Path::new(&self.root).join(path));
+        let mut archive = self.archive.borrow_mut();
+        let mut file = archive.by_name(&name).map_err(|err| match err {
+            zip::result::ZipError::FileNotFound => not_found(path),
+            err => err.into(),
+        })?;
+
+        // ヘッダーの大きさは信用できないので、確保する量には上限を設ける
+        let mut bytes = Vec::with_capacity(file.size().min(ZIP_SIZE_HINT_LIMIT) as usize);
+        file.read_to_end(&mut bytes)?;
+
+        Ok(bytes)
+    }
+}
+
+impl<T> AssetSource for &T
+where
+    T: AssetSource + ?Sized,
+{
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+}
+
+/// パスを"/"区切りの文字列にする
+/// "."を取り除き、".."は一つ前の要素と打ち消す
+pub(crate) fn normalize_path(path: &Path) -> String {
+    let mut components: Vec<String> = vec![];
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                components.pop();
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+
+    components.join("/")
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} is not found", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn normalize_path_test() {
+        assert_eq!(normalize_path(Path::new("./a/b/../c.json")), "a/c.json");
+        assert_eq!(normalize_path(Path::new("../a.json")), "a.json");
+    }
+
+    #[test]
+    fn memory_source_test() {
+        let source = MemorySource::new().with("model/a.moc3", vec![1, 2, 3]);
+
+        assert!(source.contains("./model/a.moc3"));
+        assert_eq!(
+            source.read(Path::new("model/motions/../a.moc3")).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            source.read(Path::new("b.moc3")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn zip_source_test() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        writer
+            .start_file("Hiyori/Hiyori.moc3", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(b"moc3").unwrap();
+        writer
+            .start_file(
+                "Hiyori/Hiyori.model3.json",
+                zip::write::FileOptions::default(),
+            )
+            .unwrap();
+        writer
+            .start_file(
+                "Hiyori/backup/Hiyori.model3.json",
+                zip::write::FileOptions::default(),
+            )
+            .unwrap();
+        let archive = writer.finish().unwrap();
+
+        let source = ZipSource::with_root(archive.clone(), "Hiyori").unwrap();
+        let found = source.find_model_json().unwrap();
+        assert_eq!(found, "Hiyori.model3.json");
+        assert!(source.read(Path::new(&found)).is_ok());
+        assert_eq!(source.read(Path::new("Hiyori.moc3")).unwrap(), b"moc3");
+        assert_eq!(
+            source
+                .read(Path::new("Hiyori.physics3.json"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+
+        let source = ZipSource::new(archive.clone()).unwrap();
+        assert_eq!(
+            source.find_model_json(),
+            Some("Hiyori/Hiyori.model3.json".to_string())
+        );
+        let source = ZipSource::with_root(archive, "Hiyori/other").unwrap();
+        assert_eq!(source.find_model_json(), None);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::model_resource::Live2DModelResource;
+
+/// 呼吸で揺らすパラメータ一つ分の設定
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreathParameter {
+    pub id: String,
+    /// 揺れの中心
+    pub offset: f32,
+    /// 揺れ幅
+    pub peak: f32,
+    /// 揺れの周期[秒]
+    pub cycle: f32,
+    /// パラメータに加算するときの重み
+    pub weight: f32,
+}
+
+impl BreathParameter {
+    pub fn new(id: &str, offset: f32, peak: f32, cycle: f32, weight: f32) -> Self {
+        BreathParameter {
+            id: id.to_string(),
+            offset,
+            peak,
+            cycle,
+            weight,
+        }
+    }
+
+    /// 公式サンプルと同じ設定
+    pub fn defaults() -> Vec<BreathParameter> {
+        vec![
+            BreathParameter::new("ParamAngleX", 0.0, 15.0, 6.5345, 0.5),
+            BreathParameter::new("ParamAngleY", 0.0, 8.0, 3.5345, 0.5),
+            BreathParameter::new("ParamAngleZ", 0.0, 10.0, 5.5345, 0.5),
+            BreathParameter::new("ParamBodyAngleX", 0.0, 4.0, 15.5345, 0.5),
+            BreathParameter::new("ParamBreath", 0.5, 0.5, 3.2345, 0.5),
+        ]
+    }
+
+    /// ある時間にパラメータに加算する値
+    pub fn value(&self, time: f32) -> f32 {
+        let wave = if self.cycle == 0.0 {
+            0.0
+        } else {
+            (time * std::f32::consts::TAU / self.cycle).sin()
+        };
+
+        (self.offset + self.peak * wave) * self.weight
+    }
+}
+
+/// サインカーブでパラメータを揺らして呼吸させる
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Effect/CubismBreath.cpp
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breath {
+    parameters: Vec<BreathParameter>,
+    /// モデルのパラメータの番号 モデルに存在しなければNone
+    parameter_indices: Vec<Option<usize>>,
+    /// 経過時間の合計
+    current_time: f32,
+}
+
+impl Breath {
+    pub fn new(parameters: Vec<BreathParameter>, model: &Live2DModelResource) -> Self {
+        let parameter_indices = parameters
+            .iter()
+            .map(|parameter| model.parameter_index(&parameter.id))
+            .collect();
+
+        Breath {
+            parameters,
+            parameter_indices,
+            current_time: 0.0,
+        }
+    }
+
+    /// 公式サンプルと同じ設定で生成する
+    pub fn with_default_parameters(model: &Live2DModelResource) -> Self {
+        Self::new(BreathParameter::defaults(), model)
+    }
+
+    #[inline]
+    pub fn parameters(&self) -> &[BreathParameter] {
+        &self.parameters
+    }
+
+    /// 揺らすパラメータを設定し直す
+    pub fn set_parameters(
+        &mut self,
+        parameters: Vec<BreathParameter>,
+        model: &Live2DModelResource,
+    ) {
+        *self = Breath {
+            current_time: self.current_time,
+            ..Breath::new(parameters, model)
+        };
+    }
+
+    /// 時間を進めてパラメータに加算する
+    pub fn update(&mut self, model: &mut Live2DModelResource, delta_time: f32) {
+        self.current_time += delta_time;
+
+        let parameter_values = model.csm_get_mut_parameter_values();
+        for (parameter, index) in self.parameters.iter().zip(self.parameter_indices.iter()) {
+            if let Some(index) = index {
+                parameter_values[*index] += parameter.value(self.current_time);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breath_value_test() {
+        let parameter = BreathParameter::new("ParamBreath", 0.5, 0.5, 4.0, 0.5);
+
+        assert!((parameter.value(0.0) - 0.25).abs() < 1e-6);
+        // 周期の1/4で最大、3/4で最小になる
+        assert!((parameter.value(1.0) - 0.5).abs() < 1e-6);
+        assert!((parameter.value(3.0) - 0.0).abs() < 1e-6);
+        assert!((parameter.value(4.0) - parameter.value(0.0)).abs() < 1e-5);
+
+        let parameter = BreathParameter::new("ParamAngleX", 0.0, 15.0, 0.0, 0.5);
+        assert_eq!(parameter.value(1.0), 0.0);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use crate::draw_list::{DrawCommand, DrawMesh, Transform};
+
+/// マスクのアトラスのチャンネル数 (RGBA)
+pub const MASK_CHANNEL_COUNT: usize = 4;
+/// マスクのアトラスの大きさの初期値 公式と同じ
+pub const DEFAULT_MASK_SIZE: u32 = 256;
+/// クリッピングされるdrawableの範囲に加える余白の割合
+const MASK_MARGIN: f32 = 0.05;
+
+/// 同じマスクの組み合わせを使うdrawableのまとまり
+/// マスクはアトラスのchannelのlayoutの範囲に一度だけ描画する
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Rendering/CubismClippingManager.tpp
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClippingContext {
+    /// マスクに使うdrawableの番号 並び替えて重複を取り除いてある
+    pub masks: Vec<usize>,
+    /// このマスクでクリッピングするdrawableの番号
+    pub clipped_drawables: Vec<usize>,
+    /// アトラスのどのチャンネルに描画するか 0から順にRGBA
+    pub channel: usize,
+    /// アトラス内の範囲 [x, y, width, height] (0.0から1.0)
+    pub layout: [f32; 4],
+    /// クリッピングされるdrawable全体のモデルの座標での範囲 [x, y, width, height]
+    pub bounds: [f32; 4],
+    /// モデルの座標からアトラスの座標(0.0から1.0)への変換
+    /// マスクの描画とクリッピングされるdrawableの描画の両方で使う
+    pub transform: Transform,
+}
+
+/// commandsのマスクの組み合わせごとにクリッピングの情報を作り直す
+/// 各commandのclipping_contextも設定する
+pub(crate) fn build_contexts(
+    contexts: &mut Vec<ClippingContext>,
+    commands: &mut [DrawCommand],
+    positions: &[[f32; 2]],
+    meshes: &[DrawMesh],
+) {
+    contexts.clear();
+
+    // 順番が違うだけのマスクは同じものとして扱う
+    let mut context_indices: HashMap<Vec<usize>, usize> = HashMap::new();
+    for command in commands.iter_mut() {
+        if command.masks.is_empty() {
+            command.clipping_context = None;
+            continue;
+        }
+
+        let mut masks = command.masks.clone();
+        masks.sort_unstable();
+        masks.dedup();
+
+        let index = *context_indices.entry(masks.clone()).or_insert_with(|| {
+            contexts.push(ClippingContext {
+                masks,
+                clipped_drawables: vec![],
+                channel: 0,
+                layout: [0.0, 0.0, 1.0, 1.0],
+                bounds: [0.0; 4],
+                transform: [0.0; 6],
+            });
+            contexts.len() - 1
+        });
+
+        contexts[index]
+            .clipped_drawables
+            .push(command.drawable_index);
+        command.clipping_context = Some(index);
+    }
+
+    // 公式と同じく前のチャンネルから順に多めに割り当てる
+    let layout_count = contexts.len() / MASK_CHANNEL_COUNT;
+    let remainder = contexts.len() % MASK_CHANNEL_COUNT;
+    let mut context_iter = contexts.iter_mut();
+    for channel in 0..MASK_CHANNEL_COUNT {
+        let count = layout_count + usize::from(channel < remainder);
+        for (cell, context) in context_iter.by_ref().take(count).enumerate() {
+            context.channel = channel;
+            context.layout = layout_rect(cell, count);
+        }
+    }
+
+    update_bounds(contexts, positions, meshes);
+}
+
+/// 頂点座標が変わったときにマスクの組み合わせはそのままで範囲だけ計算し直す
+pub(crate) fn update_bounds(
+    contexts: &mut [ClippingContext],
+    positions: &[[f32; 2]],
+    meshes: &[DrawMesh],
+) {
+    for context in contexts.iter_mut() {
+        context.bounds = clipped_bounds(&context.clipped_drawables, positions, meshes);
+        context.transform = layout_transform(&context.bounds, &context.layout);
+    }
+}
+
+/// 一つのチャンネルをcount個に分けたときのcell番目の範囲
+/// 2個までは縦に分割し、それ以上は格子状に分割する
+fn layout_rect(cell: usize, count: usize) -> [f32; 4] {
+    match count {
+        0 | 1 => [0.0, 0.0, 1.0, 1.0],
+        2 => [cell as f32 * 0.5, 0.0, 0.5, 1.0],
+        _ => {
+            let division = (count as f32).sqrt().ceil() as usize;
+            let size = 1.0 / division as f32;
+            [
+                (cell % division) as f32 * size,
+                (cell / division) as f32 * size,
+                size,
+                size,
+            ]
+        }
+    }
+}
+
+/// drawablesの頂点全体を囲む範囲に余白を加える
+fn clipped_bounds(drawables: &[usize], positions: &[[f32; 2]], meshes: &[DrawMesh]) -> [f32; 4] {
+    let mut min = [f32::MAX; 2];
+    let mut max = [f32::MIN; 2];
+    for mesh in drawables.iter().filter_map(|index| meshes.get(*index)) {
+        for position in positions[mesh.vertex_range.clone()].iter() {
+            min = [min[0].min(position[0]), min[1].min(position[1])];
+            max = [max[0].max(position[0]), max[1].max(position[1])];
+        }
+    }
+
+    if min[0] > max[0] || min[1] > max[1] {
+        return [0.0; 4];
+    }
+
+    let margin = [
+        (max[0] - min[0]) * MASK_MARGIN,
+        (max[1] - min[1]) * MASK_MARGIN,
+    ];
+    [
+        min[0] - margin[0],
+        min[1] - margin[1],
+        max[0] - min[0] + margin[0] * 2.0,
+        max[1] - min[1] + margin[1] * 2.0,
+    ]
+}
+
+/// boundsをlayoutに写す変換
+fn layout_transform(bounds: &[f32; 4], layout: &[f32; 4]) -> Transform {
+    let scale_x = if bounds[2] > 0.0 {
+        layout[2] / bounds[2]
+    } else {
+        0.0
+    };
+    let scale_y = if bounds[3] > 0.0 {
+        layout[3] / bounds[3]
+    } else {
+        0.0
+    };
+
+    [
+        scale_x,
+        0.0,
+        0.0,
+        scale_y,
+        layout[0] - bounds[0] * scale_x,
+        layout[1] - bounds[1] * scale_y,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw_list::apply_transform;
+
+    #[test]
+    fn build_contexts_test() {
+        let positions = [[0.0, 0.0], [1.0, 2.0], [-1.0, 0.0], [1.0, 0.0]];
+        let meshes = [
+            DrawMesh {
+                texture_index: 0,
+                vertex_range: 0..2,
+                index_range: 0..0,
+            },
+            DrawMesh {
+                texture_index: 0,
+                vertex_range: 2..4,
+                index_range: 0..0,
+            },
+        ];
+        let mut commands = vec![
+            DrawCommand {
+                drawable_index: 0,
+                masks: vec![2, 3],
+                ..Default::default()
+            },
+            DrawCommand {
+                drawable_index: 1,
+                masks: vec![3, 2, 3],
+                ..Default::default()
+            },
+            DrawCommand {
+                drawable_index: 2,
+                masks: vec![],
+                ..Default::default()
+            },
+            DrawCommand {
+                drawable_index: 3,
+                masks: vec![4],
+                ..Default::default()
+            },
+        ];
+
+        let mut contexts = vec![];
+        build_contexts(&mut contexts, &mut commands, &positions, &meshes);
+
+        // 順番と重複が違うだけのマスクはまとめる
+        assert_eq!(contexts.len(), 2);
+        assert_eq!(contexts[0].masks, vec![2, 3]);
+        assert_eq!(contexts[0].clipped_drawables, vec![0, 1]);
+        assert_eq!(commands[1].clipping_context, Some(0));
+        assert_eq!(commands[2].clipping_context, None);
+        assert_eq!(commands[3].clipping_context, Some(1));
+        assert_eq!((contexts[0].channel, contexts[1].channel), (0, 1));
+
+        // クリッピングされるdrawable全体がレイアウトに収まる
+        let [x, y, width, height] = contexts[0].bounds;
+        assert!(x < -1.0 && y < 0.0 && x + width > 1.0 && y + height > 2.0);
+        let [u, v] = apply_transform(&contexts[0].transform, [x + width, y + height]);
+        assert!((u - 1.0).abs() < 1e-5 && (v - 1.0).abs() < 1e-5);
+
+        // 頂点が動いたら範囲だけ計算し直す
+        let moved = [[0.0, 0.0], [3.0, 2.0], [-1.0, 0.0], [1.0, 0.0]];
+        update_bounds(&mut contexts, &moved, &meshes);
+        assert_eq!(contexts[0].masks, vec![2, 3]);
+        assert!(contexts[0].bounds[0] + contexts[0].bounds[2] > 3.0);
+    }
+
+    #[test]
+    fn layout_test() {
+        assert_eq!(layout_rect(0, 1), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(layout_rect(1, 2), [0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(layout_rect(3, 4), [0.5, 0.5, 0.5, 0.5]);
+        assert_eq!(layout_rect(4, 9)[0], 1.0 / 3.0);
+
+        // 6個の場合は前の2チャンネルを半分に分ける
+        let mut commands = (0..6)
+            .map(|index| DrawCommand {
+                drawable_index: index,
+                masks: vec![index],
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut contexts = vec![];
+        build_contexts(&mut contexts, &mut commands, &[], &[]);
+        let channels = contexts
+            .iter()
+            .map(|context| context.channel)
+            .collect::<Vec<_>>();
+        assert_eq!(channels, vec![0, 0, 1, 1, 2, 3]);
+        assert_eq!(contexts[1].layout, [0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(contexts[4].layout, [0.0, 0.0, 1.0, 1.0]);
+    }
+}
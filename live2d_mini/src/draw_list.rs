@@ -0,0 +1,340 @@
+use std::ops::Range;
+
+use crate::clipping::{self, ClippingContext};
+use crate::constant_flag::Live2DConstantFlag;
+use crate::model_resource::{Live2DColor, Live2DModelResource};
+use crate::vector2::Live2DVector2;
+
+/// 2次元のアフィン変換
+/// [a, b, c, d, e, f]のとき x' = a * x + c * y + e, y' = b * x + d * y + f
+pub type Transform = [f32; 6];
+
+/// 何もしない変換
+pub const IDENTITY_TRANSFORM: Transform = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+#[inline]
+pub fn apply_transform(transform: &Transform, [x, y]: [f32; 2]) -> [f32; 2] {
+    [
+        transform[0] * x + transform[2] * y + transform[4],
+        transform[1] * x + transform[3] * y + transform[5],
+    ]
+}
+
+/// drawableの合成方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// 通常 (アルファブレンド)
+    #[default]
+    Normal,
+    /// 加算
+    Additive,
+    /// 乗算
+    Multiplicative,
+}
+
+impl From<&Live2DConstantFlag> for BlendMode {
+    fn from(flag: &Live2DConstantFlag) -> Self {
+        if flag.is_csm_blend_additive() {
+            BlendMode::Additive
+        } else if flag.is_csm_blend_multiplicative() {
+            BlendMode::Multiplicative
+        } else {
+            BlendMode::Normal
+        }
+    }
+}
+
+/// drawable一つ分の頂点とインデックスの範囲
+/// indicesの値はvertex_rangeの先頭からの番号
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DrawMesh {
+    /// マスクに使う場合もこのテクスチャのアルファを使う
+    pub texture_index: usize,
+    pub vertex_range: Range<usize>,
+    pub index_range: Range<usize>,
+}
+
+/// drawable一つ分の描画命令
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawCommand {
+    /// Live2DModelResourceでのdrawableの番号 DrawList::meshの引数
+    pub drawable_index: usize,
+    pub texture_index: usize,
+    pub blend_mode: BlendMode,
+    pub opacity: f32,
+    pub multiply_color: Live2DColor,
+    pub screen_color: Live2DColor,
+    /// trueの場合は裏面を描画しない
+    pub culling: bool,
+    /// マスクに使うdrawableの番号 空の場合はマスクなし
+    pub masks: Vec<usize>,
+    /// マスクの外側を描画する
+    pub inverted_mask: bool,
+    /// DrawList::clipping_contextsの番号 マスクなしの場合はNone
+    pub clipping_context: Option<usize>,
+}
+
+impl DrawCommand {
+    #[inline]
+    pub fn is_masked(&self) -> bool {
+        !self.masks.is_empty()
+    }
+}
+
+/// 色を変えない不透明なマスクなしの描画命令
+impl Default for DrawCommand {
+    fn default() -> Self {
+        DrawCommand {
+            drawable_index: 0,
+            texture_index: 0,
+            blend_mode: BlendMode::Normal,
+            opacity: 1.0,
+            multiply_color: Live2DColor::new(1.0, 1.0, 1.0, 1.0),
+            screen_color: Live2DColor::new(0.0, 0.0, 0.0, 1.0),
+            culling: false,
+            masks: vec![],
+            inverted_mask: false,
+            clipping_context: None,
+        }
+    }
+}
+
+/// レンダラーに依存しない1フレーム分の描画内容
+/// commandsは描画する順番に並んでいる
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DrawList {
+    /// 全drawableの頂点座標
+    pub positions: Vec<[f32; 2]>,
+    /// 全drawableのuv座標
+    pub uvs: Vec<[f32; 2]>,
+    /// 全drawableのインデックス
+    pub indices: Vec<u16>,
+    /// drawableの番号ごとの頂点とインデックスの範囲
+    /// 非表示のdrawableもマスクに使われるので含める
+    pub meshes: Vec<DrawMesh>,
+    pub commands: Vec<DrawCommand>,
+    /// マスクの組み合わせごとのクリッピングの情報
+    pub clipping_contexts: Vec<ClippingContext>,
+    /// 直前のbuildかupdateで頂点座標が変わったdrawableの番号
+    /// buildの後は全てのdrawableになる
+    pub changed_meshes: Vec<usize>,
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// モデルの現在の状態から生成する
+    pub fn from_resource(resource: &Live2DModelResource) -> Self {
+        let mut draw_list = Self::new();
+        draw_list.build(resource);
+        draw_list
+    }
+
+    /// モデルの現在の状態で作り直す
+    /// 確保済みのメモリは再利用する
+    pub fn build(&mut self, resource: &Live2DModelResource) {
+        self.clear();
+
+        let drawable_count = resource.csm_get_drawable_count();
+        let texture_indices = resource.csm_get_drawable_texture_indices();
+        for (index, texture_index) in texture_indices.iter().enumerate() {
+            self.push_mesh(
+                *texture_index as usize,
+                resource.drawable_vertex_positions(index),
+                resource.drawable_vertex_uvs(index),
+                resource.drawable_indices(index),
+            );
+        }
+        self.changed_meshes.extend(0..drawable_count);
+
+        self.push_commands(resource);
+        self.update_clipping();
+    }
+
+    /// 前回のLive2DModelResource::updateで変わったところだけ書き換える
+    /// 頂点座標は変わったdrawableだけ、描画順は変わったときだけ並べ直す
+    /// フラグは次のupdateで消えるので、モデルを更新するたびに呼ぶ
+    pub fn update(&mut self, resource: &Live2DModelResource) {
+        let drawable_count = resource.csm_get_drawable_count();
+        if self.meshes.len() != drawable_count {
+            self.build(resource);
+            return;
+        }
+
+        let dynamic_flags = resource.csm_get_drawable_dynamic_flags();
+        self.changed_meshes.clear();
+        for (index, flag) in dynamic_flags.iter().enumerate() {
+            if !flag.is_csm_vertex_positions_did_change() {
+                continue;
+            }
+
+            let range = self.meshes[index].vertex_range.clone();
+            for (position, vertex) in self.positions[range]
+                .iter_mut()
+                .zip(resource.drawable_vertex_positions(index))
+            {
+                *position = [vertex.x(), vertex.y()];
+            }
+            self.changed_meshes.push(index);
+        }
+
+        let order_changed = dynamic_flags.iter().any(|flag| {
+            flag.is_csm_draw_order_did_change()
+                || flag.is_csm_render_order_did_change()
+                || flag.is_csm_visibility_did_change()
+        });
+        if order_changed {
+            self.commands.clear();
+            self.push_commands(resource);
+            self.update_clipping();
+            return;
+        }
+
+        // 色の上書きはフラグに出ないので毎回読み直す
+        let opacities = resource.csm_get_drawable_opacities();
+        for command in self.commands.iter_mut() {
+            let index = command.drawable_index;
+            command.opacity = opacities[index];
+            command.multiply_color = resource.multiply_color(index);
+            command.screen_color = resource.screen_color(index);
+        }
+
+        if !self.changed_meshes.is_empty() {
+            clipping::update_bounds(&mut self.clipping_contexts, &self.positions, &self.meshes);
+        }
+    }
+
+    /// 表示するdrawableの描画命令を描画する順番に追加する
+    fn push_commands(&mut self, resource: &Live2DModelResource) {
+        let drawable_count = resource.csm_get_drawable_count();
+        let texture_indices = resource.csm_get_drawable_texture_indices();
+        let constant_flags = resource.csm_get_drawable_constant_flags();
+        let opacities = resource.csm_get_drawable_opacities();
+
+        for drawable in resource.iter_sorted_drawables() {
+            let index = drawable.index();
+            if !drawable.dynamic_flag().is_csm_is_visible() || drawable.indices().is_none() {
+                continue;
+            }
+
+            let constant_flag = &constant_flags[index];
+            self.commands.push(DrawCommand {
+                drawable_index: index,
+                texture_index: texture_indices[index] as usize,
+                blend_mode: BlendMode::from(constant_flag),
+                opacity: opacities[index],
+                multiply_color: drawable.multiply_color(),
+                screen_color: drawable.screen_color(),
+                culling: !constant_flag.is_csm_is_double_sided(),
+                masks: drawable
+                    .masks()
+                    .iter()
+                    .filter(|mask| **mask >= 0 && (**mask as usize) < drawable_count)
+                    .map(|mask| *mask as usize)
+                    .collect(),
+                inverted_mask: constant_flag.is_csm_is_inverted_mask(),
+                clipping_context: None,
+            });
+        }
+    }
+
+    /// commandsのマスクからクリッピングの情報を作り直す
+    /// buildの中で呼ぶので、commandsを直接書き換えた場合だけ呼べばよい
+    pub fn update_clipping(&mut self) {
+        clipping::build_contexts(
+            &mut self.clipping_contexts,
+            &mut self.commands,
+            &self.positions,
+            &self.meshes,
+        );
+    }
+
+    /// commandのクリッピングの情報
+    pub fn clipping_context(&self, command: &DrawCommand) -> Option<&ClippingContext> {
+        self.clipping_contexts.get(command.clipping_context?)
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.uvs.clear();
+        self.indices.clear();
+        self.meshes.clear();
+        self.commands.clear();
+        self.clipping_contexts.clear();
+        self.changed_meshes.clear();
+    }
+
+    /// drawable_indexのdrawableの頂点とインデックスの範囲
+    #[inline]
+    pub fn mesh(&self, drawable_index: usize) -> &DrawMesh {
+        &self.meshes[drawable_index]
+    }
+
+    /// drawable_indexのdrawableの頂点座標
+    pub fn mesh_positions(&self, drawable_index: usize) -> &[[f32; 2]] {
+        &self.positions[self.mesh(drawable_index).vertex_range.clone()]
+    }
+
+    /// drawable_indexのdrawableのuv座標
+    pub fn mesh_uvs(&self, drawable_index: usize) -> &[[f32; 2]] {
+        &self.uvs[self.mesh(drawable_index).vertex_range.clone()]
+    }
+
+    /// drawable_indexのdrawableのインデックス
+    pub fn mesh_indices(&self, drawable_index: usize) -> &[u16] {
+        &self.indices[self.mesh(drawable_index).index_range.clone()]
+    }
+
+    fn push_mesh(
+        &mut self,
+        texture_index: usize,
+        positions: &[Live2DVector2],
+        uvs: &[Live2DVector2],
+        indices: &[u16],
+    ) {
+        let vertex_start = self.positions.len();
+        let index_start = self.indices.len();
+
+        self.positions.extend(
+            positions
+                .iter()
+                .map(|position| [position.x(), position.y()]),
+        );
+        self.uvs.extend(uvs.iter().map(|uv| [uv.x(), uv.y()]));
+        self.indices.extend_from_slice(indices);
+
+        self.meshes.push(DrawMesh {
+            texture_index,
+            vertex_range: vertex_start..self.positions.len(),
+            index_range: index_start..self.indices.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_mesh_test() {
+        let mut draw_list = DrawList::new();
+        let triangle = [
+            Live2DVector2::new(0.0, 0.0),
+            Live2DVector2::new(1.0, 0.0),
+            Live2DVector2::new(0.0, 1.0),
+        ];
+        draw_list.push_mesh(0, &triangle, &triangle, &[0, 1, 2]);
+        draw_list.push_mesh(0, &[], &[], &[]);
+        draw_list.push_mesh(0, &triangle[..2], &triangle[..2], &[1, 0, 1]);
+
+        assert_eq!(draw_list.mesh(0).vertex_range, 0..3);
+        assert_eq!(draw_list.mesh(1).vertex_range, 3..3);
+        assert_eq!(draw_list.mesh(2).vertex_range, 3..5);
+        assert_eq!(draw_list.mesh(2).index_range, 3..6);
+        assert_eq!(draw_list.mesh_positions(2), &[[0.0, 0.0], [1.0, 0.0]]);
+        assert_eq!(draw_list.mesh_indices(2), &[1, 0, 1]);
+        assert!(draw_list.mesh_uvs(1).is_empty());
+    }
+}
@@ -8,6 +8,8 @@ use crate::vector2::*;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Live2DDrawable<'a> {
+    /// Live2DModelResourceでの番号
+    index: usize,
     id: &'a *const c_char,
     constant_flag: &'a Live2DConstantFlag,
     dynamic_flag: &'a Live2DDynamicFlag,
@@ -18,14 +20,23 @@ pub struct Live2DDrawable<'a> {
     mask_count: &'a i32,
     vertex_count: &'a i32,
     index_count: &'a i32,
-
-    masks: &'a *const i32,
-    indices: &'a *const u16,
-    vertex_positions: &'a *const Live2DVector2,
-    vertex_uvs: &'a *const Live2DVector2,
+    /// 上書きを反映した乗算色
+    multiply_color: Live2DColor,
+    /// 上書きを反映したスクリーン色
+    screen_color: Live2DColor,
+
+    masks: &'a [i32],
+    indices: &'a [u16],
+    vertex_positions: &'a [Live2DVector2],
+    vertex_uvs: &'a [Live2DVector2],
 }
 
 impl<'a> Live2DDrawable<'a> {
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
     #[inline]
     pub fn id(&self) -> &str {
         unsafe { CStr::from_ptr(*self.id).to_str().expect("id error") }
@@ -77,36 +88,41 @@ impl<'a> Live2DDrawable<'a> {
     }
 
     #[inline]
-    pub fn masks(&self) -> &[i32] {
-        unsafe { std::slice::from_raw_parts(*self.masks, *self.mask_count() as usize) }
+    pub fn multiply_color(&self) -> Live2DColor {
+        self.multiply_color
+    }
+
+    #[inline]
+    pub fn screen_color(&self) -> Live2DColor {
+        self.screen_color
+    }
+
+    #[inline]
+    pub fn masks(&self) -> &'a [i32] {
+        self.masks
     }
 
     #[inline]
-    pub fn indices(&self) -> Option<&[u16]> {
-        if *self.index_count() == 0 {
+    pub fn indices(&self) -> Option<&'a [u16]> {
+        if self.indices.is_empty() {
             return None;
         }
 
-        unsafe {
-            Some(std::slice::from_raw_parts(
-                *self.indices,
-                *self.index_count() as usize,
-            ))
-        }
+        Some(self.indices)
     }
 
     #[inline]
-    pub fn vertex_positions(&self) -> &[Live2DVector2] {
-        unsafe { std::slice::from_raw_parts(*self.vertex_positions, *self.vertex_count() as usize) }
+    pub fn vertex_positions(&self) -> &'a [Live2DVector2] {
+        self.vertex_positions
     }
 
     #[inline]
-    pub fn vertex_uvs(&self) -> &[Live2DVector2] {
-        unsafe { std::slice::from_raw_parts(*self.vertex_uvs, *self.vertex_count() as usize) }
+    pub fn vertex_uvs(&self) -> &'a [Live2DVector2] {
+        self.vertex_uvs
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Live2DDrawableIter<'a> {
     pub(crate) pos: usize,
     pub(crate) len: usize,
@@ -127,6 +143,7 @@ impl<'a> Iterator for Live2DDrawableIter<'a> {
 
             unsafe {
                 Some(Live2DDrawable {
+                    index: pos,
                     id: self.inner.csm_get_drawable_ids().get_unchecked(pos),
                     constant_flag: self
                         .inner
@@ -155,14 +172,13 @@ impl<'a> Iterator for Live2DDrawableIter<'a> {
                         .inner
                         .csm_get_drawable_dynamic_flags()
                         .get_unchecked(pos),
+                    multiply_color: self.inner.multiply_color(pos),
+                    screen_color: self.inner.screen_color(pos),
 
-                    masks: self.inner.csm_get_drawable_masks().get_unchecked(pos),
-                    indices: self.inner.csm_get_drawable_indices().get_unchecked(pos),
-                    vertex_positions: self
-                        .inner
-                        .csm_get_drawable_vertex_positions()
-                        .get_unchecked(pos),
-                    vertex_uvs: self.inner.csm_get_drawable_vertex_uvs().get_unchecked(pos),
+                    masks: self.inner.drawable_masks(pos),
+                    indices: self.inner.drawable_indices(pos),
+                    vertex_positions: self.inner.drawable_vertex_positions(pos),
+                    vertex_uvs: self.inner.drawable_vertex_uvs(pos),
                 })
             }
         }
@@ -183,6 +199,7 @@ impl<'a> Iterator for Live2DSortedDrawableIter<'a> {
         match self.sorted_indices.pop() {
             Some(index) => unsafe {
                 Some(Live2DDrawable {
+                    index,
                     id: self.inner.csm_get_drawable_ids().get_unchecked(index),
                     constant_flag: self
                         .inner
@@ -217,17 +234,13 @@ impl<'a> Iterator for Live2DSortedDrawableIter<'a> {
                         .inner
                         .csm_get_drawable_dynamic_flags()
                         .get_unchecked(index),
+                    multiply_color: self.inner.multiply_color(index),
+                    screen_color: self.inner.screen_color(index),
 
-                    masks: self.inner.csm_get_drawable_masks().get_unchecked(index),
-                    indices: self.inner.csm_get_drawable_indices().get_unchecked(index),
-                    vertex_positions: self
-                        .inner
-                        .csm_get_drawable_vertex_positions()
-                        .get_unchecked(index),
-                    vertex_uvs: self
-                        .inner
-                        .csm_get_drawable_vertex_uvs()
-                        .get_unchecked(index),
+                    masks: self.inner.drawable_masks(index),
+                    indices: self.inner.drawable_indices(index),
+                    vertex_positions: self.inner.drawable_vertex_positions(index),
+                    vertex_uvs: self.inner.drawable_vertex_uvs(index),
                 })
             },
             None => return None,
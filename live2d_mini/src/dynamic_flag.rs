@@ -44,6 +44,17 @@ impl Live2DDynamicFlag {
         }
     }
 
+    #[inline]
+    pub fn is_csm_render_order_did_change(&self) -> bool {
+        if (self.0 as u32 & live2d_mini_sys::csmRenderOrderDidChange)
+            == live2d_mini_sys::csmRenderOrderDidChange
+        {
+            true
+        } else {
+            false
+        }
+    }
+
     #[inline]
     pub fn is_csm_vertex_positions_did_change(&self) -> bool {
         if (self.0 as u32 & live2d_mini_sys::csmVertexPositionsDidChange)
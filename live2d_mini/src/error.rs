@@ -0,0 +1,307 @@
+use std::alloc::LayoutError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// ファイルの読み込み・デシリアライズ時のエラー
+#[derive(Debug)]
+pub enum Live2DFileError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// motion3.jsonのSegmentsが不正
+    Segment(Live2DSegmentError),
+}
+
+impl fmt::Display for Live2DFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Live2DFileError::Io(err) => write!(f, "{}", err),
+            Live2DFileError::Json(err) => write!(f, "{}", err),
+            Live2DFileError::Segment(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Live2DFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Live2DFileError::Io(err) => Some(err),
+            Live2DFileError::Json(err) => Some(err),
+            Live2DFileError::Segment(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for Live2DFileError {
+    fn from(err: io::Error) -> Self {
+        Live2DFileError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Live2DFileError {
+    fn from(err: serde_json::Error) -> Self {
+        Live2DFileError::Json(err)
+    }
+}
+
+impl From<Live2DSegmentError> for Live2DFileError {
+    fn from(err: Live2DSegmentError) -> Self {
+        Live2DFileError::Segment(err)
+    }
+}
+
+/// motion3.jsonのSegmentsを区間に分けられない
+/// indexはSegmentsの中の位置
+#[derive(Debug, Clone, PartialEq)]
+pub enum Live2DSegmentError {
+    /// 点の途中で配列が終わっている
+    Truncated { index: usize },
+    /// 0から3以外の種類
+    UnknownType { index: usize, segment_type: f32 },
+}
+
+impl fmt::Display for Live2DSegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Live2DSegmentError::Truncated { index } => {
+                write!(f, "segments are truncated at {}", index)
+            }
+            Live2DSegmentError::UnknownType {
+                index,
+                segment_type,
+            } => write!(f, "unknown segment type {} at {}", segment_type, index),
+        }
+    }
+}
+
+impl std::error::Error for Live2DSegmentError {}
+
+/// moc3やモデル用のメモリを確保できない
+#[derive(Debug)]
+pub enum Live2DAllocationError {
+    /// 大きさかアラインメントが不正
+    Layout(LayoutError),
+    /// メモリが足りない
+    OutOfMemory { size: usize, align: usize },
+}
+
+impl fmt::Display for Live2DAllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Live2DAllocationError::Layout(err) => write!(f, "{}", err),
+            Live2DAllocationError::OutOfMemory { size, align } => {
+                write!(f, "out of memory (size {}, align {})", size, align)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Live2DAllocationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Live2DAllocationError::Layout(err) => Some(err),
+            Live2DAllocationError::OutOfMemory { .. } => None,
+        }
+    }
+}
+
+impl From<LayoutError> for Live2DAllocationError {
+    fn from(err: LayoutError) -> Self {
+        Live2DAllocationError::Layout(err)
+    }
+}
+
+/// モデル読み込みのどの段階で失敗したか
+#[derive(Debug)]
+pub enum Live2DLoadError {
+    /// zipファイルが読めない、またはmodel3.jsonが含まれていない
+    Archive { path: PathBuf, source: io::Error },
+    /// model3.jsonが読めない
+    ModelJson {
+        path: PathBuf,
+        source: Live2DFileError,
+    },
+    /// moc3ファイルが読めない
+    Moc { path: PathBuf, source: io::Error },
+    /// moc3ファイル用のメモリを確保できない
+    MocAllocation {
+        path: PathBuf,
+        source: Live2DAllocationError,
+    },
+    /// moc3ファイルの整合性チェックに失敗した
+    MocConsistency { path: PathBuf },
+    /// moc3ファイルを復元できない 対応していないバージョンなど
+    MocRevive { path: PathBuf },
+    /// mocからモデルを初期化できない
+    ModelInitialization { path: PathBuf },
+    /// テクスチャが読めない
+    Texture {
+        index: usize,
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// physics3.jsonが読めない
+    Physics {
+        path: PathBuf,
+        source: Live2DFileError,
+    },
+    /// motion3.jsonが読めない
+    Motion {
+        path: PathBuf,
+        source: Live2DFileError,
+    },
+    /// exp3.jsonが読めない
+    Expression {
+        path: PathBuf,
+        source: Live2DFileError,
+    },
+    /// pose3.jsonが読めない
+    Pose {
+        path: PathBuf,
+        source: Live2DFileError,
+    },
+}
+
+impl Live2DLoadError {
+    /// 失敗したファイルのパス
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            Live2DLoadError::Archive { path, .. }
+            | Live2DLoadError::ModelJson { path, .. }
+            | Live2DLoadError::Moc { path, .. }
+            | Live2DLoadError::MocAllocation { path, .. }
+            | Live2DLoadError::MocConsistency { path }
+            | Live2DLoadError::MocRevive { path }
+            | Live2DLoadError::ModelInitialization { path }
+            | Live2DLoadError::Texture { path, .. }
+            | Live2DLoadError::Physics { path, .. }
+            | Live2DLoadError::Motion { path, .. }
+            | Live2DLoadError::Expression { path, .. }
+            | Live2DLoadError::Pose { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Live2DLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Live2DLoadError::Archive { path, source } => {
+                write!(f, "cannot load archive {}: {}", path.display(), source)
+            }
+            Live2DLoadError::ModelJson { path, source } => {
+                write!(f, "cannot load model3.json {}: {}", path.display(), source)
+            }
+            Live2DLoadError::Moc { path, source } => {
+                write!(f, "cannot read moc3 {}: {}", path.display(), source)
+            }
+            Live2DLoadError::MocAllocation { path, source } => {
+                write!(f, "cannot allocate moc3 {}: {}", path.display(), source)
+            }
+            Live2DLoadError::MocConsistency { path } => {
+                write!(f, "moc3 file is not consistent: {}", path.display())
+            }
+            Live2DLoadError::MocRevive { path } => {
+                write!(f, "cannot revive moc3: {}", path.display())
+            }
+            Live2DLoadError::ModelInitialization { path } => {
+                write!(f, "cannot initialize model from moc3: {}", path.display())
+            }
+            Live2DLoadError::Texture {
+                index,
+                path,
+                source,
+            } => write!(
+                f,
+                "cannot load texture {} {}: {}",
+                index,
+                path.display(),
+                source
+            ),
+            Live2DLoadError::Physics { path, source } => {
+                write!(
+                    f,
+                    "cannot load physics3.json {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Live2DLoadError::Motion { path, source } => {
+                write!(f, "cannot load motion3.json {}: {}", path.display(), source)
+            }
+            Live2DLoadError::Expression { path, source } => {
+                write!(f, "cannot load exp3.json {}: {}", path.display(), source)
+            }
+            Live2DLoadError::Pose { path, source } => {
+                write!(f, "cannot load pose3.json {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Live2DLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Live2DLoadError::Archive { source, .. } => Some(source),
+            Live2DLoadError::ModelJson { source, .. } => Some(source),
+            Live2DLoadError::Moc { source, .. } => Some(source),
+            Live2DLoadError::MocAllocation { source, .. } => Some(source),
+            Live2DLoadError::MocConsistency { .. } => None,
+            Live2DLoadError::MocRevive { .. } => None,
+            Live2DLoadError::ModelInitialization { .. } => None,
+            Live2DLoadError::Texture { source, .. } => Some(source),
+            Live2DLoadError::Physics { source, .. } => Some(source),
+            Live2DLoadError::Motion { source, .. } => Some(source),
+            Live2DLoadError::Expression { source, .. } => Some(source),
+            Live2DLoadError::Pose { source, .. } => Some(source),
+        }
+    }
+}
+
+/// 画像の書き出しに失敗した
+#[derive(Debug)]
+pub enum Live2DExportError {
+    /// 指定した番号のアニメーションがない
+    NoAnimation { index: usize },
+    /// 出力先のファイルやディレクトリを作れない
+    Io { path: PathBuf, source: io::Error },
+    /// PNGやGIFに変換できない
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// APNGに変換できない
+    Apng {
+        path: PathBuf,
+        source: png::EncodingError,
+    },
+}
+
+impl fmt::Display for Live2DExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Live2DExportError::NoAnimation { index } => {
+                write!(f, "animation {} is not found", index)
+            }
+            Live2DExportError::Io { path, source } => {
+                write!(f, "cannot create {}: {}", path.display(), source)
+            }
+            Live2DExportError::Image { path, source } => {
+                write!(f, "cannot write image {}: {}", path.display(), source)
+            }
+            Live2DExportError::Apng { path, source } => {
+                write!(f, "cannot write apng {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Live2DExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Live2DExportError::NoAnimation { .. } => None,
+            Live2DExportError::Io { source, .. } => Some(source),
+            Live2DExportError::Image { source, .. } => Some(source),
+            Live2DExportError::Apng { source, .. } => Some(source),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::error::Live2DExportError;
+use crate::model::Live2DModel;
+use crate::rasterizer::Rasterizer;
+
+/// モデルの現在の状態を描画してPNGで保存する
+/// 大きさと背景色はrasterizerの設定を使う
+pub fn render_png<P>(
+    model: &Live2DModel,
+    rasterizer: &mut Rasterizer,
+    path: P,
+) -> Result<(), Live2DExportError>
+where
+    P: AsRef<Path>,
+{
+    save_png(&rasterizer.render_model(model), path)
+}
+
+pub fn save_png<P>(image: &RgbaImage, path: P) -> Result<(), Live2DExportError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    image
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|source| Live2DExportError::Image {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// アニメーションを最初からfpsごとに描画していくイテレータ
+/// モデルの他のモーションは止め、物理演算やまばたきはモデルの設定どおりに動かす
+#[derive(Debug)]
+pub struct MotionFrames<'a> {
+    model: &'a mut Live2DModel,
+    rasterizer: &'a mut Rasterizer,
+    fps: f32,
+    frame: usize,
+    frame_count: usize,
+}
+
+impl<'a> MotionFrames<'a> {
+    /// Live2DModel::animationsのanimation_index番目を描画する
+    /// fpsは1.0より小さい場合1.0にする
+    pub fn new(
+        model: &'a mut Live2DModel,
+        rasterizer: &'a mut Rasterizer,
+        animation_index: usize,
+        fps: f32,
+    ) -> Result<Self, Live2DExportError> {
+        let duration = model
+            .animations
+            .get(animation_index)
+            .map(|animation| animation.duration)
+            .ok_or(Live2DExportError::NoAnimation {
+                index: animation_index,
+            })?;
+        let fps = fps.max(1.0);
+
+        model.stop_all_motions();
+        model.reset_animation(animation_index);
+
+        Ok(MotionFrames {
+            model,
+            rasterizer,
+            fps,
+            frame: 0,
+            frame_count: frame_count(duration, fps),
+        })
+    }
+
+    #[inline]
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+impl<'a> Iterator for MotionFrames<'a> {
+    type Item = RgbaImage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.frame >= self.frame_count {
+            return None;
+        }
+
+        let delta_time = if self.frame == 0 { 0.0 } else { 1.0 / self.fps };
+        self.frame += 1;
+        self.model.update(delta_time);
+
+        Some(self.rasterizer.render_model(self.model))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.frame_count - self.frame;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for MotionFrames<'a> {}
+
+/// dirに連番のPNGで保存して保存したパスを返す
+/// ファイル名はframe_0000.pngから始まる
+pub fn save_png_sequence<I, P>(frames: I, dir: P) -> Result<Vec<PathBuf>, Live2DExportError>
+where
+    I: ExactSizeIterator<Item = RgbaImage>,
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|source| Live2DExportError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+
+    let digits = frames.len().to_string().len().max(4);
+    let mut paths = Vec::with_capacity(frames.len());
+    for (index, frame) in frames.enumerate() {
+        let path = dir.join(format!("frame_{:0digits$}.png", index, digits = digits));
+        save_png(&frame, &path)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// 無限にループするアニメーションGIFで保存する
+/// GIFは色数が256色までなので、きれいに残したい場合はAPNGを使う
+pub fn save_gif<I, P>(frames: I, fps: f32, path: P) -> Result<(), Live2DExportError>
+where
+    I: Iterator<Item = RgbaImage>,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let image_error = |source| Live2DExportError::Image {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let file = File::create(path).map_err(|source| Live2DExportError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut encoder = GifEncoder::new(BufWriter::new(file));
+    encoder.set_repeat(Repeat::Infinite).map_err(image_error)?;
+
+    let (numerator, denominator) = frame_delay(fps);
+    let delay = Delay::from_numer_denom_ms(numerator as u32 * 1000, denominator as u32);
+    for frame in frames {
+        encoder
+            .encode_frame(Frame::from_parts(frame, 0, 0, delay))
+            .map_err(image_error)?;
+    }
+
+    Ok(())
+}
+
+/// 無限にループするAPNGで保存する
+/// 全てのフレームは同じ大きさにする
+pub fn save_apng<I, P>(frames: I, fps: f32, path: P) -> Result<(), Live2DExportError>
+where
+    I: ExactSizeIterator<Item = RgbaImage>,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let apng_error = |source| Live2DExportError::Apng {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let mut frames = frames.peekable();
+    let (width, height) = frames
+        .peek()
+        .map(|frame| frame.dimensions())
+        .unwrap_or((1, 1));
+    let frame_count = frames.len() as u32;
+
+    let file = File::create(path).map_err(|source| Live2DExportError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frame_count, 0).map_err(apng_error)?;
+    let (numerator, denominator) = frame_delay(fps);
+    encoder
+        .set_frame_delay(numerator, denominator)
+        .map_err(apng_error)?;
+
+    let mut writer = encoder.write_header().map_err(apng_error)?;
+    for frame in frames {
+        writer
+            .write_image_data(frame.as_raw())
+            .map_err(apng_error)?;
+    }
+
+    writer.finish().map_err(apng_error)
+}
+
+/// durationをfpsで描画するときのフレーム数
+/// ループするアニメーションがつながるように最後の時間は含めない
+fn frame_count(duration: f32, fps: f32) -> usize {
+    ((duration * fps).ceil() as usize).max(1)
+}
+
+/// 1フレームの秒数を分数で表す
+fn frame_delay(fps: f32) -> (u16, u16) {
+    (
+        100,
+        (fps * 100.0).round().clamp(1.0, u16::MAX as f32) as u16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{AnimationDecoder, Rgba};
+
+    fn frames() -> Vec<RgbaImage> {
+        [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 128])]
+            .into_iter()
+            .map(|color| RgbaImage::from_pixel(4, 3, color))
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let name = format!("live2d_mini_{}_{}", name, std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn frame_count_test() {
+        assert_eq!(frame_count(1.0, 30.0), 30);
+        assert_eq!(frame_count(1.01, 30.0), 31);
+        assert_eq!(frame_count(0.0, 30.0), 1);
+        assert_eq!(frame_delay(30.0), (100, 3000));
+        assert_eq!(frame_delay(29.97), (100, 2997));
+    }
+
+    #[test]
+    fn export_test() {
+        let dir = temp_dir("export_test");
+
+        let paths = save_png_sequence(frames().into_iter(), dir.join("sequence")).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths[1].ends_with("frame_0001.png"));
+        let image = image::open(&paths[1]).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 128]));
+
+        let gif_path = dir.join("motion.gif");
+        save_gif(frames().into_iter(), 30.0, &gif_path).unwrap();
+        let decoder = image::codecs::gif::GifDecoder::new(File::open(&gif_path).unwrap()).unwrap();
+        assert_eq!(decoder.into_frames().count(), 2);
+
+        let apng_path = dir.join("motion.png");
+        save_apng(frames().into_iter(), 30.0, &apng_path).unwrap();
+        let reader = png::Decoder::new(File::open(&apng_path).unwrap())
+            .read_info()
+            .unwrap();
+        let animation = reader.info().animation_control().unwrap();
+        assert_eq!((animation.num_frames, animation.num_plays), (2, 0));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+use crate::animation::{AnimationFade, DEFAULT_FADE_TIME};
+use crate::expression_json;
+use crate::model_resource::Live2DModelResource;
+
+/// 表情のパラメータの適用方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpressionBlend {
+    /// 現在の値に加算する
+    #[default]
+    Add,
+    /// 現在の値に乗算する
+    Multiply,
+    /// 現在の値を上書きする
+    Overwrite,
+}
+
+impl ExpressionBlend {
+    fn parse(blend: Option<&str>) -> Self {
+        match blend {
+            Some("Multiply") => ExpressionBlend::Multiply,
+            Some("Overwrite") => ExpressionBlend::Overwrite,
+            _ => ExpressionBlend::Add,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionParameter {
+    pub id: String,
+    pub value: f32,
+    pub blend: ExpressionBlend,
+    /// モデルのパラメータの番号 モデルに存在しなければNone
+    parameter_index: Option<usize>,
+}
+
+/// exp3.json一つ分の表情
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub name: String,
+    pub fade_in_time: f32,
+    pub fade_out_time: f32,
+    pub parameters: Vec<ExpressionParameter>,
+}
+
+impl Expression {
+    pub fn new(
+        name: &str,
+        json: &expression_json::ExpressionJson,
+        model: &Live2DModelResource,
+    ) -> Self {
+        let parameters = json
+            .Parameters
+            .iter()
+            .flatten()
+            .map(|parameter| ExpressionParameter {
+                id: parameter.Id.clone(),
+                value: parameter.Value,
+                blend: ExpressionBlend::parse(parameter.Blend.as_deref()),
+                parameter_index: model.parameter_index(&parameter.Id),
+            })
+            .collect();
+
+        Expression {
+            name: name.to_string(),
+            fade_in_time: json
+                .FadeInTime
+                .filter(|time| *time >= 0.0)
+                .unwrap_or(DEFAULT_FADE_TIME),
+            fade_out_time: json
+                .FadeOutTime
+                .filter(|time| *time >= 0.0)
+                .unwrap_or(DEFAULT_FADE_TIME),
+            parameters,
+        }
+    }
+}
+
+/// 再生中の表情一つ分の状態
+#[derive(Debug, Clone, PartialEq)]
+struct ExpressionQueueEntry {
+    expression_index: usize,
+    /// フェードインを開始した時刻
+    fade_in_start_time: f32,
+    /// フェードアウトが終わる時刻
+    end_time: Option<f32>,
+    /// 直前のupdateでの重み
+    fade_weight: f32,
+}
+
+/// 表情を重ねるときの計算途中の値
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ExpressionValue {
+    parameter_index: usize,
+    additive: f32,
+    multiply: f32,
+    overwrite: f32,
+}
+
+/// 表情をクロスフェードさせながらパラメータに適用する
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Motion/CubismExpressionMotionManager.cpp
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExpressionManager {
+    entries: Vec<ExpressionQueueEntry>,
+    /// 経過時間の合計
+    user_time: f32,
+    /// 使い回すための作業領域
+    values: Vec<ExpressionValue>,
+}
+
+impl ExpressionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 表情を開始する
+    /// 前の表情はこの表情のフェードインが終わるまで残る
+    pub fn start_expression(&mut self, expression_index: usize) {
+        self.entries.push(ExpressionQueueEntry {
+            expression_index,
+            fade_in_start_time: self.user_time,
+            end_time: None,
+            fade_weight: 0.0,
+        });
+    }
+
+    /// 全ての表情をフェードアウトさせる
+    pub fn stop_expressions(&mut self, expressions: &[Expression]) {
+        for entry in self.entries.iter_mut() {
+            let fade_out_time = expressions
+                .get(entry.expression_index)
+                .map(|expression| expression.fade_out_time)
+                .unwrap_or(0.0);
+            let end_time = self.user_time + fade_out_time;
+            if entry.end_time.map(|time| end_time < time).unwrap_or(true) {
+                entry.end_time = Some(end_time);
+            }
+        }
+    }
+
+    /// 最後に開始した表情の番号
+    pub fn current_expression(&self) -> Option<usize> {
+        self.entries
+            .last()
+            .filter(|entry| entry.end_time.is_none())
+            .map(|entry| entry.expression_index)
+    }
+
+    /// 時間を進めて表情をパラメータに適用する
+    /// 表情を適用した場合はtrueを返す
+    pub fn update(
+        &mut self,
+        expressions: &[Expression],
+        model: &mut Live2DModelResource,
+        delta_time: f32,
+    ) -> bool {
+        self.user_time += delta_time;
+        if self.entries.is_empty() {
+            return false;
+        }
+
+        self.blend_parameters(expressions, model.csm_get_mut_parameter_values());
+        true
+    }
+
+    /// 再生中の表情をフェードの重みで重ねてparameter_valuesに書き込む
+    fn blend_parameters(&mut self, expressions: &[Expression], parameter_values: &mut [f32]) {
+        // 再生中の表情が使うパラメータを集める
+        self.values.clear();
+        for entry in self.entries.iter() {
+            let expression = match expressions.get(entry.expression_index) {
+                Some(expression) => expression,
+                None => continue,
+            };
+            for parameter_index in expression
+                .parameters
+                .iter()
+                .filter_map(|parameter| parameter.parameter_index)
+            {
+                if self
+                    .values
+                    .iter()
+                    .all(|value| value.parameter_index != parameter_index)
+                {
+                    self.values.push(ExpressionValue {
+                        parameter_index,
+                        additive: 0.0,
+                        multiply: 1.0,
+                        overwrite: parameter_values[parameter_index],
+                    });
+                }
+            }
+        }
+
+        let user_time = self.user_time;
+        for entry in self.entries.iter_mut() {
+            let expression = match expressions.get(entry.expression_index) {
+                Some(expression) => expression,
+                None => continue,
+            };
+
+            let fade = AnimationFade {
+                fade_in_elapsed: user_time - entry.fade_in_start_time,
+                fade_out_remaining: entry.end_time.map(|end_time| end_time - user_time),
+            };
+            let weight = fade.weight(expression.fade_in_time, expression.fade_out_time);
+            entry.fade_weight = weight;
+
+            for value in self.values.iter_mut() {
+                let current = parameter_values[value.parameter_index];
+                let (additive, multiply, overwrite) = match expression
+                    .parameters
+                    .iter()
+                    .find(|parameter| parameter.parameter_index == Some(value.parameter_index))
+                {
+                    Some(parameter) => match parameter.blend {
+                        ExpressionBlend::Add => (parameter.value, 1.0, current),
+                        ExpressionBlend::Multiply => (0.0, parameter.value, current),
+                        ExpressionBlend::Overwrite => (0.0, 1.0, parameter.value),
+                    },
+                    None => (0.0, 1.0, current),
+                };
+
+                value.additive = lerp(value.additive, additive, weight);
+                value.multiply = lerp(value.multiply, multiply, weight);
+                value.overwrite = lerp(value.overwrite, overwrite, weight);
+            }
+        }
+
+        for value in self.values.iter() {
+            parameter_values[value.parameter_index] =
+                (value.overwrite + value.additive) * value.multiply;
+        }
+
+        // フェードアウトが終わった表情と
+        // 最新の表情のフェードインが終わったらそれより前の表情を取り除く
+        self.entries
+            .retain(|entry| entry.end_time.map(|time| time >= user_time).unwrap_or(true));
+        if let Some(last) = self.entries.last() {
+            if last.fade_weight >= 1.0 && last.end_time.is_none() {
+                let len = self.entries.len();
+                self.entries.drain(..len - 1);
+            }
+        }
+    }
+}
+
+#[inline]
+fn lerp(source: f32, destination: f32, weight: f32) -> f32 {
+    source * (1.0 - weight) + destination * weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expression(parameters: &[(usize, ExpressionBlend, f32)]) -> Expression {
+        Expression {
+            name: String::new(),
+            fade_in_time: 1.0,
+            fade_out_time: 1.0,
+            parameters: parameters
+                .iter()
+                .map(|(index, blend, value)| ExpressionParameter {
+                    id: format!("Param{}", index),
+                    value: *value,
+                    blend: *blend,
+                    parameter_index: Some(*index),
+                })
+                .collect(),
+        }
+    }
+
+    fn assert_values(actual: &[f32], expected: &[f32]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "{:?} {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn blend_parameters_test() {
+        let expressions = [
+            expression(&[
+                (0, ExpressionBlend::Add, 0.5),
+                (1, ExpressionBlend::Multiply, 2.0),
+                (2, ExpressionBlend::Overwrite, 10.0),
+            ]),
+            expression(&[(0, ExpressionBlend::Add, 1.0)]),
+        ];
+        let mut manager = ExpressionManager::new();
+        manager.start_expression(0);
+
+        // フェードインの途中は重み0.5
+        // Add: (1 + 0.5 * 0.5) * 1, Multiply: 2 * (1 + (2 - 1) * 0.5), Overwrite: 3 + (10 - 3) * 0.5
+        let mut values = [1.0, 2.0, 3.0];
+        manager.user_time += 0.5;
+        manager.blend_parameters(&expressions, &mut values);
+        assert_values(&values, &[1.25, 3.0, 6.5]);
+
+        let mut values = [1.0, 2.0, 3.0];
+        manager.user_time += 0.5;
+        manager.blend_parameters(&expressions, &mut values);
+        assert_values(&values, &[1.5, 4.0, 10.0]);
+
+        // 次の表情の重み0.5で前の表情から補間する
+        // Add: 1 + (0.5 + (1 - 0.5) * 0.5), Multiply: 2 * (2 + (1 - 2) * 0.5), Overwrite: 10 + (3 - 10) * 0.5
+        manager.start_expression(1);
+        let mut values = [1.0, 2.0, 3.0];
+        manager.user_time += 0.5;
+        manager.blend_parameters(&expressions, &mut values);
+        assert_values(&values, &[1.75, 3.0, 6.5]);
+        assert_eq!(manager.entries.len(), 2);
+
+        // フェードインが終わると前の表情は取り除かれる
+        let mut values = [1.0, 2.0, 3.0];
+        manager.user_time += 0.5;
+        manager.blend_parameters(&expressions, &mut values);
+        assert_values(&values, &[2.0, 2.0, 3.0]);
+        assert_eq!(manager.current_expression(), Some(1));
+        assert_eq!(manager.entries.len(), 1);
+    }
+}
@@ -0,0 +1,19 @@
+#![allow(non_snake_case)]
+
+use serde::*;
+
+#[derive(Debug, Deserialize)]
+pub struct ExpressionJson {
+    pub Type: String,
+    pub FadeInTime: Option<f32>,
+    pub FadeOutTime: Option<f32>,
+    pub Parameters: Option<Vec<Parameter>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Parameter {
+    pub Id: String,
+    pub Value: f32,
+    /// Add, Multiply, Overwriteのいずれか 省略時はAdd
+    pub Blend: Option<String>,
+}
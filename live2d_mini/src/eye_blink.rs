@@ -0,0 +1,263 @@
+use crate::model_resource::Live2DModelResource;
+
+/// これより短いとまばたきの時刻が過去になる[秒]
+const MIN_BLINKING_INTERVAL: f32 = 0.5;
+
+/// まばたきの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EyeBlinkState {
+    /// 初期状態
+    First,
+    /// まばたきしていない
+    Interval,
+    /// まぶたが閉じていく途中
+    Closing,
+    /// まぶたが閉じている
+    Closed,
+    /// まぶたが開いていく途中
+    Opening,
+}
+
+/// 自動まばたき
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Effect/CubismEyeBlink.cpp
+#[derive(Debug, Clone)]
+pub struct EyeBlink {
+    parameter_ids: Vec<String>,
+    /// モデルに存在するパラメータの番号
+    parameter_indices: Vec<usize>,
+    enabled: bool,
+
+    state: EyeBlinkState,
+    /// 次にまばたきする時刻
+    next_blinking_time: f32,
+    /// 現在の状態になった時刻
+    state_start_time: f32,
+    /// 経過時間の合計
+    user_time: f32,
+
+    /// まばたきの間隔[秒]
+    blinking_interval: f32,
+    /// まぶたを閉じるのにかかる時間[秒]
+    closing_time: f32,
+    /// まぶたを閉じている時間[秒]
+    closed_time: f32,
+    /// まぶたを開くのにかかる時間[秒]
+    opening_time: f32,
+
+    rng: fastrand::Rng,
+}
+
+impl EyeBlink {
+    /// model3.jsonのEyeBlinkグループのパラメータidから生成する
+    pub fn new(parameter_ids: Vec<String>, model: &Live2DModelResource) -> Self {
+        let parameter_indices = parameter_ids
+            .iter()
+            .filter_map(|id| model.parameter_index(id))
+            .collect();
+
+        EyeBlink {
+            parameter_ids,
+            parameter_indices,
+            enabled: true,
+
+            state: EyeBlinkState::First,
+            next_blinking_time: 0.0,
+            state_start_time: 0.0,
+            user_time: 0.0,
+
+            blinking_interval: 4.0,
+            closing_time: 0.1,
+            closed_time: 0.05,
+            opening_time: 0.15,
+
+            rng: fastrand::Rng::new(),
+        }
+    }
+
+    #[inline]
+    pub fn parameter_ids(&self) -> &[String] {
+        &self.parameter_ids
+    }
+
+    #[inline]
+    pub fn state(&self) -> EyeBlinkState {
+        self.state
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// falseにするとまばたきを止めて目を開いた状態に戻す
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.state = EyeBlinkState::First;
+        }
+    }
+
+    /// まばたきの間隔を設定する
+    /// 実際の間隔は0から(2 * interval - 1)秒までのランダムな値になる
+    /// 0.5秒より短い間隔は0.5秒にする
+    pub fn set_blinking_interval(&mut self, interval: f32) {
+        self.blinking_interval = interval.max(MIN_BLINKING_INTERVAL);
+    }
+
+    /// まばたきの各動作にかかる時間を設定する
+    pub fn set_blinking_settings(
+        &mut self,
+        closing_time: f32,
+        closed_time: f32,
+        opening_time: f32,
+    ) {
+        self.closing_time = closing_time;
+        self.closed_time = closed_time;
+        self.opening_time = opening_time;
+    }
+
+    /// 乱数のシードを設定する
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    fn determine_next_blinking_time(&mut self) -> f32 {
+        self.user_time + self.rng.f32() * (2.0 * self.blinking_interval - 1.0)
+    }
+
+    /// 時間を進めてまぶたの開き具合を計算する
+    /// 1.0で開いている、0.0で閉じている
+    pub fn evaluate(&mut self, delta_time: f32) -> f32 {
+        self.user_time += delta_time;
+
+        match self.state {
+            EyeBlinkState::Closing => {
+                let mut t = self.elapsed_ratio(self.closing_time);
+                if t >= 1.0 {
+                    t = 1.0;
+                    self.state = EyeBlinkState::Closed;
+                    self.state_start_time = self.user_time;
+                }
+
+                1.0 - t
+            }
+            EyeBlinkState::Closed => {
+                let t = self.elapsed_ratio(self.closed_time);
+                if t >= 1.0 {
+                    self.state = EyeBlinkState::Opening;
+                    self.state_start_time = self.user_time;
+                }
+
+                0.0
+            }
+            EyeBlinkState::Opening => {
+                let mut t = self.elapsed_ratio(self.opening_time);
+                if t >= 1.0 {
+                    t = 1.0;
+                    self.state = EyeBlinkState::Interval;
+                    self.next_blinking_time = self.determine_next_blinking_time();
+                }
+
+                t
+            }
+            EyeBlinkState::Interval => {
+                if self.next_blinking_time < self.user_time {
+                    self.state = EyeBlinkState::Closing;
+                    self.state_start_time = self.user_time;
+                }
+
+                1.0
+            }
+            EyeBlinkState::First => {
+                self.state = EyeBlinkState::Interval;
+                self.next_blinking_time = self.determine_next_blinking_time();
+
+                1.0
+            }
+        }
+    }
+
+    /// 時間を進めてまばたきのパラメータに掛け合わせる
+    /// モーションで目を閉じている場合はそのまま閉じた状態になる
+    pub fn update(&mut self, model: &mut Live2DModelResource, delta_time: f32) {
+        if !self.enabled {
+            return;
+        }
+
+        let value = self.evaluate(delta_time);
+        let parameter_values = model.csm_get_mut_parameter_values();
+        for index in self.parameter_indices.iter() {
+            parameter_values[*index] *= value;
+        }
+    }
+
+    fn elapsed_ratio(&self, time: f32) -> f32 {
+        if time <= 0.0 {
+            1.0
+        } else {
+            (self.user_time - self.state_start_time) / time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eye_blink() -> EyeBlink {
+        EyeBlink {
+            parameter_ids: vec![],
+            parameter_indices: vec![],
+            enabled: true,
+            state: EyeBlinkState::First,
+            next_blinking_time: 0.0,
+            state_start_time: 0.0,
+            user_time: 0.0,
+            blinking_interval: 4.0,
+            closing_time: 0.1,
+            closed_time: 0.05,
+            opening_time: 0.15,
+            rng: fastrand::Rng::with_seed(0),
+        }
+    }
+
+    #[test]
+    fn blink_cycle_test() {
+        let mut eye_blink = eye_blink();
+
+        assert_eq!(eye_blink.evaluate(0.0), 1.0);
+        assert_eq!(eye_blink.state(), EyeBlinkState::Interval);
+        assert!(eye_blink.next_blinking_time < 7.0);
+
+        // 次のまばたきまで進める
+        eye_blink.evaluate(8.0);
+        assert_eq!(eye_blink.state(), EyeBlinkState::Closing);
+
+        assert!((eye_blink.evaluate(0.05) - 0.5).abs() < 1e-3);
+        assert_eq!(eye_blink.evaluate(0.1), 0.0);
+        assert_eq!(eye_blink.state(), EyeBlinkState::Closed);
+
+        assert_eq!(eye_blink.evaluate(0.1), 0.0);
+        assert_eq!(eye_blink.state(), EyeBlinkState::Opening);
+
+        assert_eq!(eye_blink.evaluate(0.2), 1.0);
+        assert_eq!(eye_blink.state(), EyeBlinkState::Interval);
+    }
+
+    #[test]
+    fn blinking_interval_test() {
+        let mut eye_blink = eye_blink();
+
+        // 短すぎる間隔でも次のまばたきが過去にならない
+        eye_blink.set_blinking_interval(0.1);
+        assert_eq!(eye_blink.blinking_interval, 0.5);
+        for _ in 0..100 {
+            assert!(eye_blink.determine_next_blinking_time() >= eye_blink.user_time);
+        }
+
+        eye_blink.set_blinking_interval(3.0);
+        for _ in 0..100 {
+            assert!(eye_blink.determine_next_blinking_time() < 5.0);
+        }
+    }
+}
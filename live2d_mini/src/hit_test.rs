@@ -0,0 +1,160 @@
+use crate::model_resource::Live2DModelResource;
+use crate::vector2::Live2DVector2;
+
+/// 当たり判定の方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HitTestMode {
+    /// drawableの頂点を囲む矩形 公式と同じ
+    #[default]
+    BoundingBox,
+    /// drawableのメッシュの三角形の内側
+    Triangle,
+}
+
+/// model3.jsonのHitAreas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HitArea {
+    pub name: String,
+    /// 判定に使うdrawableのid
+    pub id: String,
+    /// idのdrawableの番号 モデルにない場合はNone
+    pub drawable_index: Option<usize>,
+}
+
+impl HitArea {
+    pub fn new(name: &str, id: &str, resource: &Live2DModelResource) -> Self {
+        HitArea {
+            name: name.to_string(),
+            id: id.to_string(),
+            drawable_index: resource.drawable_index(id),
+        }
+    }
+}
+
+/// drawable_indexのdrawableの今の頂点座標にpositionが含まれるか
+/// positionはモデルの座標
+pub fn hit_drawable(
+    resource: &Live2DModelResource,
+    drawable_index: usize,
+    position: [f32; 2],
+    mode: HitTestMode,
+) -> bool {
+    if drawable_index >= resource.csm_get_drawable_count() {
+        return false;
+    }
+
+    let positions = resource.drawable_vertex_positions(drawable_index);
+    match mode {
+        HitTestMode::BoundingBox => bounds(positions)
+            .map(|[left, bottom, right, top]| {
+                left <= position[0]
+                    && position[0] <= right
+                    && bottom <= position[1]
+                    && position[1] <= top
+            })
+            .unwrap_or(false),
+        HitTestMode::Triangle => resource
+            .drawable_indices(drawable_index)
+            .chunks_exact(3)
+            .any(|triangle| contains_triangle(positions, triangle, position)),
+    }
+}
+
+/// positionにある表示中のdrawableの番号を手前から順に返す
+pub fn pick_drawables(
+    resource: &Live2DModelResource,
+    position: [f32; 2],
+    mode: HitTestMode,
+) -> Vec<usize> {
+    // iter_sorted_drawablesは奥から順に並んでいる
+    let mut drawables = resource
+        .iter_sorted_drawables()
+        .filter(|drawable| {
+            drawable.dynamic_flag().is_csm_is_visible() && *drawable.opacitiy() > 0.0
+        })
+        .map(|drawable| drawable.index())
+        .filter(|index| hit_drawable(resource, *index, position, mode))
+        .collect::<Vec<_>>();
+    drawables.reverse();
+    drawables
+}
+
+/// 頂点を囲む矩形 [left, bottom, right, top]
+fn bounds(positions: &[Live2DVector2]) -> Option<[f32; 4]> {
+    let first = positions.first()?;
+    Some(positions.iter().fold(
+        [first.x(), first.y(), first.x(), first.y()],
+        |[left, bottom, right, top], position| {
+            [
+                left.min(position.x()),
+                bottom.min(position.y()),
+                right.max(position.x()),
+                top.max(position.y()),
+            ]
+        },
+    ))
+}
+
+/// 三角形の辺の上も含む 頂点の向きはどちらでもよい
+fn contains_triangle(positions: &[Live2DVector2], triangle: &[u16], [x, y]: [f32; 2]) -> bool {
+    let vertex = |index: u16| {
+        positions
+            .get(index as usize)
+            .map(|position| [position.x(), position.y()])
+    };
+    let (a, b, c) = match (
+        vertex(triangle[0]),
+        vertex(triangle[1]),
+        vertex(triangle[2]),
+    ) {
+        (Some(a), Some(b), Some(c)) => (a, b, c),
+        _ => return false,
+    };
+
+    // 面積のない三角形は線の延長上も含んでしまうので除く
+    let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+    if area == 0.0 {
+        return false;
+    }
+
+    let edge = |from: [f32; 2], to: [f32; 2]| {
+        (to[0] - from[0]) * (y - from[1]) - (to[1] - from[1]) * (x - from[0])
+    };
+    let (ab, bc, ca) = (edge(a, b), edge(b, c), edge(c, a));
+
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_test() {
+        let positions = [
+            Live2DVector2::new(0.0, 1.0),
+            Live2DVector2::new(-1.0, 0.5),
+            Live2DVector2::new(2.0, -1.0),
+        ];
+        assert_eq!(bounds(&positions), Some([-1.0, -1.0, 2.0, 1.0]));
+        assert_eq!(bounds(&[]), None);
+    }
+
+    #[test]
+    fn contains_triangle_test() {
+        let positions = [
+            Live2DVector2::new(0.0, 0.0),
+            Live2DVector2::new(2.0, 0.0),
+            Live2DVector2::new(0.0, 2.0),
+        ];
+
+        assert!(contains_triangle(&positions, &[0, 1, 2], [0.5, 0.5]));
+        // 逆向きの三角形と辺の上
+        assert!(contains_triangle(&positions, &[0, 2, 1], [0.5, 0.5]));
+        assert!(contains_triangle(&positions, &[0, 1, 2], [1.0, 1.0]));
+        // 矩形の中でも三角形の外
+        assert!(!contains_triangle(&positions, &[0, 1, 2], [1.5, 1.5]));
+        assert!(!contains_triangle(&positions, &[0, 1, 3], [0.5, 0.5]));
+        assert!(!contains_triangle(&positions, &[0, 1, 1], [3.0, 0.0]));
+    }
+}
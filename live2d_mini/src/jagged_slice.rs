@@ -0,0 +1,122 @@
+/// Cubism Coreが返す配列の配列
+/// 要素ごとの先頭のポインタと長さの組を安全に扱う
+#[derive(Debug)]
+pub struct JaggedSlice<'a, T> {
+    pointers: &'a [*const T],
+    lengths: &'a [i32],
+}
+
+// Tに関わらずコピーできる
+impl<'a, T> Clone for JaggedSlice<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for JaggedSlice<'a, T> {}
+
+impl<'a, T> JaggedSlice<'a, T> {
+    /// # Safety
+    /// pointersの各要素はlengthsの同じ位置の数だけ有効な要素を指していて、
+    /// 'aの間は書き換えられないこと
+    pub(crate) unsafe fn new(pointers: &'a [*const T], lengths: &'a [i32]) -> Self {
+        debug_assert_eq!(pointers.len(), lengths.len());
+
+        JaggedSlice { pointers, lengths }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pointers.len().min(self.lengths.len())
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// index番目の配列
+    pub fn get(&self, index: usize) -> Option<&'a [T]> {
+        let pointer = *self.pointers.get(index)?;
+        let length = *self.lengths.get(index)?;
+
+        // 要素がない場合はnullのこともある
+        if length <= 0 || pointer.is_null() {
+            return Some(&[]);
+        }
+
+        unsafe { Some(std::slice::from_raw_parts(pointer, length as usize)) }
+    }
+
+    pub fn iter(&self) -> JaggedSliceIter<'a, T> {
+        JaggedSliceIter {
+            inner: *self,
+            pos: 0,
+        }
+    }
+}
+
+impl<'a, T> std::ops::Index<usize> for JaggedSlice<'a, T> {
+    type Output = [T];
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index).expect("index out of range")
+    }
+}
+
+impl<'a, T> IntoIterator for JaggedSlice<'a, T> {
+    type Item = &'a [T];
+    type IntoIter = JaggedSliceIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct JaggedSliceIter<'a, T> {
+    inner: JaggedSlice<'a, T>,
+    pos: usize,
+}
+
+impl<'a, T> Iterator for JaggedSliceIter<'a, T> {
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.get(self.pos)?;
+        self.pos += 1;
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inner.len().saturating_sub(self.pos);
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for JaggedSliceIter<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jagged_slice_test() {
+        let a = [1, 2, 3];
+        let b = [4];
+        let pointers = [a.as_ptr(), std::ptr::null(), b.as_ptr()];
+        let lengths = [3, 0, 1];
+        let jagged = unsafe { JaggedSlice::new(&pointers, &lengths) };
+
+        assert_eq!(jagged.len(), 3);
+        assert_eq!(jagged.get(0), Some(&[1, 2, 3][..]));
+        assert_eq!(jagged.get(1), Some(&[][..]));
+        assert_eq!(&jagged[2], &[4]);
+        assert_eq!(jagged.get(3), None);
+        assert_eq!(
+            jagged.iter().map(|slice| slice.len()).collect::<Vec<_>>(),
+            vec![3, 0, 1]
+        );
+    }
+}
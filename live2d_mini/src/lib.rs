@@ -1,15 +1,36 @@
 mod address;
 mod animation;
+pub mod asset;
+pub mod breath;
+pub mod clipping;
 mod constant_flag;
+pub mod draw_list;
 mod drawable;
 mod dynamic_flag;
+pub mod error;
+pub mod export;
+pub mod expression;
+mod expression_json;
+pub mod eye_blink;
+pub mod hit_test;
+pub mod jagged_slice;
+pub mod lip_sync;
+pub mod matrix;
+pub mod moc;
+#[cfg(feature = "miniquad")]
+pub mod miniquad_renderer;
 pub mod model;
 mod model_json;
 pub mod model_resource;
 mod motion_json;
+pub mod motion_manager;
+pub mod motion_queue;
 mod parameter;
 mod part;
 mod physic_json;
+pub mod pose;
 mod pose_json;
+pub mod rasterizer;
+pub mod target_point;
 mod vector2;
 mod physic;
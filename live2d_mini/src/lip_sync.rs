@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::animation::{
+    Animation, AnimationCurve, AnimationCurveType, AnimationPoint, AnimationType,
+    DEFAULT_FADE_TIME, LIP_SYNC_CURVE_ID,
+};
+use crate::model_resource::Live2DModelResource;
+
+/// 口パクの値をパラメータに加算するときの重み
+const DEFAULT_GAIN: f32 = 0.8;
+/// 口パクの値が目標値に近づくまでの時間[秒]
+const DEFAULT_SMOOTHING_TIME: f32 = 0.05;
+
+/// 音声の大きさに合わせて口を動かす
+/// see: https://github.com/Live2D/CubismNativeSamples/blob/develop/Samples/OpenGL/Demo/proj.win.cmake/src/LAppModel.cpp
+#[derive(Debug, Clone, PartialEq)]
+pub struct LipSync {
+    parameter_ids: Vec<String>,
+    /// モデルに存在するパラメータの番号
+    parameter_indices: Vec<usize>,
+    enabled: bool,
+
+    /// 最後に与えられた音量
+    target: f32,
+    /// なめらかにした音量
+    value: f32,
+
+    /// パラメータに加算するときの重み
+    gain: f32,
+    /// 目標値に近づくまでの時間[秒] 0ならなめらかにしない
+    smoothing_time: f32,
+}
+
+impl LipSync {
+    /// model3.jsonのLipSyncグループのパラメータidから生成する
+    pub fn new(parameter_ids: Vec<String>, model: &Live2DModelResource) -> Self {
+        let parameter_indices = parameter_ids
+            .iter()
+            .filter_map(|id| model.parameter_index(id))
+            .collect();
+
+        LipSync {
+            parameter_ids,
+            parameter_indices,
+            enabled: true,
+            target: 0.0,
+            value: 0.0,
+            gain: DEFAULT_GAIN,
+            smoothing_time: DEFAULT_SMOOTHING_TIME,
+        }
+    }
+
+    #[inline]
+    pub fn parameter_ids(&self) -> &[String] {
+        &self.parameter_ids
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// falseにすると口パクを止める
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.target = 0.0;
+            self.value = 0.0;
+        }
+    }
+
+    /// パラメータに加算するときの重みを設定する
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    /// 目標値に近づくまでの時間を設定する
+    pub fn set_smoothing_time(&mut self, smoothing_time: f32) {
+        self.smoothing_time = smoothing_time;
+    }
+
+    /// なめらかにした現在の値
+    #[inline]
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// 音量(RMS)を設定する
+    pub fn set_rms(&mut self, rms: f32) {
+        self.target = rms.clamp(0.0, 1.0);
+    }
+
+    /// PCMのサンプルから音量を計算して設定する
+    pub fn set_samples(&mut self, samples: &[f32]) {
+        self.set_rms(rms(samples));
+    }
+
+    /// 時間を進めて値をなめらかにする
+    pub fn evaluate(&mut self, delta_time: f32) -> f32 {
+        if self.smoothing_time <= 0.0 {
+            self.value = self.target;
+        } else {
+            let t = 1.0 - (-delta_time.max(0.0) / self.smoothing_time).exp();
+            self.value += (self.target - self.value) * t;
+        }
+
+        self.value
+    }
+
+    /// 時間を進めて口パクのパラメータに加算する
+    pub fn update(&mut self, model: &mut Live2DModelResource, delta_time: f32) {
+        if !self.enabled {
+            return;
+        }
+
+        let value = self.evaluate(delta_time) * self.gain;
+        let parameter_values = model.csm_get_mut_parameter_values();
+        for index in self.parameter_indices.iter() {
+            parameter_values[*index] += value;
+        }
+    }
+}
+
+/// サンプルの二乗平均平方根
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f32 = samples.iter().map(|sample| sample * sample).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+/// PCMのwavファイル
+#[derive(Debug, Clone, PartialEq)]
+pub struct WavAudio {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// -1.0から1.0に正規化したチャンネルごとに交互に並んだサンプル
+    pub samples: Vec<f32>,
+}
+
+impl WavAudio {
+    /// wavファイルを読み込む
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// wavファイルの中身から読み込む
+    /// 8, 16, 24, 32bitの整数と32bitの浮動小数点数のPCMに対応する
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(invalid_data("not a wav file"));
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = read_u32(bytes, offset + 4) as usize;
+            let start = offset + 8;
+            let end = start.saturating_add(size).min(bytes.len());
+            let chunk = &bytes[start..end];
+
+            match id {
+                b"fmt " => format = Some(WavFormat::parse(chunk)?),
+                b"data" => data = Some(chunk),
+                _ => {}
+            }
+
+            // チャンクは2byte境界に揃えられている
+            offset = start.saturating_add(size).saturating_add(size & 1);
+        }
+
+        let format = format.ok_or_else(|| invalid_data("fmt chunk not found"))?;
+        let data = data.ok_or_else(|| invalid_data("data chunk not found"))?;
+
+        let bytes_per_sample = (format.bits_per_sample / 8) as usize;
+        let samples = data
+            .chunks_exact(bytes_per_sample)
+            .map(|sample| format.decode(sample))
+            .collect();
+
+        Ok(WavAudio {
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            samples,
+        })
+    }
+
+    /// 再生時間[秒]
+    pub fn duration(&self) -> f32 {
+        self.frame_count() as f32 / self.sample_rate as f32
+    }
+
+    fn frame_count(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    /// startからlength秒間の音量
+    /// lengthが0以下なら0.0を返す
+    pub fn rms(&self, start: f32, length: f32) -> f32 {
+        let channels = self.channels.max(1) as usize;
+        let frame_count = self.frame_count();
+        let first = ((start.max(0.0) * self.sample_rate as f32) as usize).min(frame_count);
+        let last = (((start + length).max(0.0) * self.sample_rate as f32) as usize)
+            .clamp(first, frame_count);
+
+        rms(&self.samples[first * channels..last * channels])
+    }
+
+    /// 1秒にframe_rate回ずつ計算した音量
+    /// frame_rateは0より大きい有限の値にする
+    pub fn rms_frames(&self, frame_rate: f32) -> io::Result<Vec<f32>> {
+        if !frame_rate.is_finite() || frame_rate <= 0.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame rate must be positive",
+            ));
+        }
+
+        let length = 1.0 / frame_rate;
+        let count = (self.duration() * frame_rate).ceil() as usize;
+
+        Ok((0..count)
+            .map(|i| self.rms(i as f32 * length, length))
+            .collect())
+    }
+
+    /// 音量をLipSyncカーブにしたアニメーションを生成する
+    /// モデルのlip_sync_parameter_idsを設定してからモーションとして再生する
+    pub fn lip_sync_animation(&self, frame_rate: f32) -> io::Result<Animation> {
+        let length = 1.0 / frame_rate;
+        let points = self
+            .rms_frames(frame_rate)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, rms)| AnimationPoint {
+                time: i as f32 * length,
+                value: rms.clamp(0.0, 1.0),
+            })
+            .collect::<Vec<AnimationPoint>>();
+        // 1フレームより短い音声はその音量のまま動かさない
+        let segments = match points.as_slice() {
+            [point] => vec![AnimationCurveType::Linear(*point, *point)],
+            points => points
+                .windows(2)
+                .map(|points| AnimationCurveType::Linear(points[0], points[1]))
+                .collect(),
+        };
+
+        let mut curves = HashMap::new();
+        curves.insert(
+            LIP_SYNC_CURVE_ID.to_string(),
+            AnimationCurve {
+                curve_type: AnimationType::ModelAnimationCurve,
+                segments,
+                evaluated_index: 0,
+                fade_in_time: None,
+                fade_out_time: None,
+                target_index: None,
+            },
+        );
+
+        Ok(Animation {
+            duration: self.duration(),
+            is_loop: false,
+            curve_count: 1,
+            fade_in_time: DEFAULT_FADE_TIME,
+            fade_out_time: DEFAULT_FADE_TIME,
+            eye_blink_parameter_ids: vec![],
+            lip_sync_parameter_ids: vec![],
+            curves,
+        })
+    }
+}
+
+/// wavファイルのfmtチャンク
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WavFormat {
+    /// 1: 整数PCM 3: 浮動小数点数PCM
+    format_tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+/// 拡張フォーマットを表すformat_tag
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+impl WavFormat {
+    fn parse(chunk: &[u8]) -> io::Result<Self> {
+        if chunk.len() < 16 {
+            return Err(invalid_data("fmt chunk is too short"));
+        }
+
+        let mut format_tag = read_u16(chunk, 0);
+        // 拡張フォーマットはSubFormatの先頭がformat_tagになっている
+        if format_tag == WAVE_FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+            format_tag = read_u16(chunk, 24);
+        }
+
+        let format = WavFormat {
+            format_tag,
+            channels: read_u16(chunk, 2),
+            sample_rate: read_u32(chunk, 4),
+            bits_per_sample: read_u16(chunk, 14),
+        };
+
+        match (format.format_tag, format.bits_per_sample) {
+            (1, 8) | (1, 16) | (1, 24) | (1, 32) | (3, 32) => {}
+            _ => return Err(invalid_data("unsupported wav format")),
+        }
+        if format.channels == 0 || format.sample_rate == 0 {
+            return Err(invalid_data("invalid wav format"));
+        }
+
+        Ok(format)
+    }
+
+    /// サンプル一つを-1.0から1.0に正規化する
+    fn decode(&self, sample: &[u8]) -> f32 {
+        match (self.format_tag, self.bits_per_sample) {
+            (3, _) => f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]),
+            // 8bitだけ符号なし
+            (_, 8) => (sample[0] as f32 - 128.0) / 128.0,
+            (_, 16) => i16::from_le_bytes([sample[0], sample[1]]) as f32 / 32768.0,
+            (_, 24) => {
+                i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) as f32 / 2147483648.0
+            }
+            _ => {
+                i32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]) as f32
+                    / 2147483648.0
+            }
+        }
+    }
+}
+
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16bitモノラルのwavファイルを作る
+    fn wav_bytes(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data_size = samples.len() as u32 * 2;
+        let mut bytes = vec![];
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn wav_rms_test() {
+        // 前半は無音、後半は半分の振幅
+        let mut samples = vec![0i16; 100];
+        samples.extend([16384i16, -16384].iter().cycle().take(100));
+        let wav = WavAudio::from_bytes(&wav_bytes(100, &samples)).unwrap();
+
+        assert_eq!(wav.sample_rate, 100);
+        assert_eq!(wav.channels, 1);
+        assert_eq!(wav.samples.len(), 200);
+        assert_eq!(wav.duration(), 2.0);
+
+        let frames = wav.rms_frames(2.0).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], 0.0);
+        assert!((frames[3] - 0.5).abs() < 1e-4);
+
+        let animation = wav.lip_sync_animation(2.0).unwrap();
+        assert_eq!(animation.duration, 2.0);
+        let curve = animation.curves.get(LIP_SYNC_CURVE_ID).unwrap();
+        assert_eq!(curve.curve_type, AnimationType::ModelAnimationCurve);
+        assert_eq!(curve.segments.len(), 3);
+
+        assert!(WavAudio::from_bytes(b"RIFF").is_err());
+    }
+
+    #[test]
+    fn wav_rms_range_test() {
+        let wav = WavAudio::from_bytes(&wav_bytes(100, &[16384; 100])).unwrap();
+
+        assert!((wav.rms(0.5, 0.1) - 0.5).abs() < 1e-4);
+        assert_eq!(wav.rms(0.5, -0.2), 0.0);
+        assert_eq!(wav.rms(0.5, 0.0), 0.0);
+        assert_eq!(wav.rms(2.0, 1.0), 0.0);
+        assert_eq!(wav.rms(-1.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn lip_sync_animation_test() {
+        let wav = WavAudio::from_bytes(&wav_bytes(100, &[16384, -16384])).unwrap();
+
+        assert!(wav.rms_frames(0.0).is_err());
+        assert!(wav.lip_sync_animation(-30.0).is_err());
+        assert!(wav.lip_sync_animation(f32::NAN).is_err());
+
+        // 1フレームしかない音声は一定の値になる
+        let mut animation = wav.lip_sync_animation(30.0).unwrap();
+        let curve = animation.curves.get_mut(LIP_SYNC_CURVE_ID).unwrap();
+        assert_eq!(curve.segments.len(), 1);
+        assert!((curve.evaluate_curve(0.0) - 0.5).abs() < 1e-4);
+        assert!((curve.evaluate_curve(0.01) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn smoothing_test() {
+        let mut lip_sync = LipSync {
+            parameter_ids: vec![],
+            parameter_indices: vec![],
+            enabled: true,
+            target: 0.0,
+            value: 0.0,
+            gain: DEFAULT_GAIN,
+            smoothing_time: DEFAULT_SMOOTHING_TIME,
+        };
+
+        lip_sync.set_samples(&[0.5, -0.5, 0.5, -0.5]);
+        let first = lip_sync.evaluate(1.0 / 60.0);
+        assert!(first > 0.0 && first < 0.5);
+        let second = lip_sync.evaluate(1.0 / 60.0);
+        assert!(second > first && second < 0.5);
+        assert!((lip_sync.evaluate(1.0) - 0.5).abs() < 1e-4);
+
+        lip_sync.set_smoothing_time(0.0);
+        lip_sync.set_rms(0.0);
+        assert_eq!(lip_sync.evaluate(0.0), 0.0);
+    }
+}
@@ -0,0 +1,582 @@
+use crate::draw_list::Transform;
+use crate::model_resource::Live2DCanvasInfo;
+
+/// 列優先の4x4行列
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Math/CubismMatrix44.cpp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix44 {
+    tr: [f32; 16],
+}
+
+impl Default for Matrix44 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Matrix44 {
+    pub const IDENTITY: Matrix44 = Matrix44 {
+        tr: [
+            1.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0,
+        ],
+    };
+
+    pub fn new() -> Self {
+        Self::IDENTITY
+    }
+
+    #[inline]
+    pub fn from_array(tr: [f32; 16]) -> Self {
+        Matrix44 { tr }
+    }
+
+    /// 平行移動だけの行列
+    pub fn translation(x: f32, y: f32) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.translate(x, y);
+        matrix
+    }
+
+    /// 拡大だけの行列
+    pub fn scaling(x: f32, y: f32) -> Self {
+        let mut matrix = Self::IDENTITY;
+        matrix.scale(x, y);
+        matrix
+    }
+
+    /// シェーダーにそのまま渡せる配列
+    #[inline]
+    pub fn as_array(&self) -> &[f32; 16] {
+        &self.tr
+    }
+
+    pub fn load_identity(&mut self) {
+        *self = Self::IDENTITY;
+    }
+
+    /// self * other
+    /// otherで変換してからselfで変換する行列になる
+    pub fn multiply(&self, other: &Matrix44) -> Matrix44 {
+        let mut tr = [0.0; 16];
+        for column in 0..4 {
+            for row in 0..4 {
+                tr[row + column * 4] = (0..4)
+                    .map(|k| self.tr[row + k * 4] * other.tr[k + column * 4])
+                    .sum();
+            }
+        }
+
+        Matrix44 { tr }
+    }
+
+    #[inline]
+    pub fn scale_x(&self) -> f32 {
+        self.tr[0]
+    }
+
+    #[inline]
+    pub fn scale_y(&self) -> f32 {
+        self.tr[5]
+    }
+
+    #[inline]
+    pub fn translate_x(&self) -> f32 {
+        self.tr[12]
+    }
+
+    #[inline]
+    pub fn translate_y(&self) -> f32 {
+        self.tr[13]
+    }
+
+    /// 平行移動を設定する
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.tr[12] = x;
+        self.tr[13] = y;
+    }
+
+    pub fn set_translate_x(&mut self, x: f32) {
+        self.tr[12] = x;
+    }
+
+    pub fn set_translate_y(&mut self, y: f32) {
+        self.tr[13] = y;
+    }
+
+    /// 今の変換の後に平行移動する
+    pub fn translate_relative(&mut self, x: f32, y: f32) {
+        *self = Self::translation(x, y).multiply(self);
+    }
+
+    /// 拡大率を設定する 負の値で反転する
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.tr[0] = x;
+        self.tr[5] = y;
+    }
+
+    /// 今の変換の後に拡大する
+    pub fn scale_relative(&mut self, x: f32, y: f32) {
+        *self = Self::scaling(x, y).multiply(self);
+    }
+
+    /// 点を変換する
+    #[inline]
+    pub fn transform(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [
+            self.tr[0] * x + self.tr[4] * y + self.tr[12],
+            self.tr[1] * x + self.tr[5] * y + self.tr[13],
+        ]
+    }
+
+    /// transformの逆 回転やせん断は含まないものとして計算する
+    #[inline]
+    pub fn invert_transform(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [
+            (x - self.tr[12]) / self.tr[0],
+            (y - self.tr[13]) / self.tr[5],
+        ]
+    }
+
+    /// xy平面の変換だけ取り出す
+    pub fn to_transform(&self) -> Transform {
+        [
+            self.tr[0],
+            self.tr[1],
+            self.tr[4],
+            self.tr[5],
+            self.tr[12],
+            self.tr[13],
+        ]
+    }
+}
+
+/// モデルの座標をビューの座標に配置する行列
+/// 大きさと位置はキャンバス単位で指定する
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Math/CubismModelMatrix.cpp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelMatrix {
+    matrix: Matrix44,
+    /// モデルの座標でのキャンバスの左端と下端
+    left: f32,
+    bottom: f32,
+    width: f32,
+    height: f32,
+}
+
+impl ModelMatrix {
+    /// 原点が中心にあるwidth x heightのキャンバス
+    /// 公式と同じく高さを2.0にする
+    pub fn new(width: f32, height: f32) -> Self {
+        Self::with_canvas_rect(-width / 2.0, -height / 2.0, width, height)
+    }
+
+    /// モデルのキャンバスに合わせる
+    /// 高さを2.0にしてキャンバスの中心を原点に置く
+    pub fn from_canvas_info(canvas_info: &Live2DCanvasInfo) -> Self {
+        let pixels_per_unit = canvas_info.out_pixels_per_unit;
+        let left = -canvas_info.out_origin_in_pixels.x() / pixels_per_unit;
+        // モデルの座標はy軸が上向き
+        let top = canvas_info.out_origin_in_pixels.y() / pixels_per_unit;
+        let height = canvas_info.get_canvas_height();
+
+        let mut model_matrix =
+            Self::with_canvas_rect(left, top - height, canvas_info.get_canvas_with(), height);
+        model_matrix.set_center_position(0.0, 0.0);
+        model_matrix
+    }
+
+    fn with_canvas_rect(left: f32, bottom: f32, width: f32, height: f32) -> Self {
+        let mut model_matrix = ModelMatrix {
+            matrix: Matrix44::IDENTITY,
+            left,
+            bottom,
+            width,
+            height,
+        };
+        model_matrix.set_height(2.0);
+        model_matrix
+    }
+
+    #[inline]
+    pub fn matrix(&self) -> &Matrix44 {
+        &self.matrix
+    }
+
+    /// キャンバスの幅がwになるように拡大する 縦横比は保つ
+    pub fn set_width(&mut self, w: f32) {
+        if self.width > 0.0 {
+            self.set_uniform_scale(w / self.width);
+        }
+    }
+
+    /// キャンバスの高さがhになるように拡大する 縦横比は保つ
+    pub fn set_height(&mut self, h: f32) {
+        if self.height > 0.0 {
+            self.set_uniform_scale(h / self.height);
+        }
+    }
+
+    /// 反転の向きを保って拡大率を変える
+    fn set_uniform_scale(&mut self, scale: f32) {
+        self.matrix.scale(
+            scale.copysign(self.matrix.scale_x()),
+            scale.copysign(self.matrix.scale_y()),
+        );
+    }
+
+    /// 拡大率を設定する 負の値で反転する
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.matrix.scale(x, y);
+    }
+
+    /// 平行移動を設定する
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.matrix.translate(x, y);
+    }
+
+    /// キャンバスの中心をx, yに置く
+    pub fn set_center_position(&mut self, x: f32, y: f32) {
+        self.set_center_x(x);
+        self.set_center_y(y);
+    }
+
+    pub fn set_center_x(&mut self, x: f32) {
+        let center = self.left + self.width / 2.0;
+        self.matrix
+            .set_translate_x(x - self.matrix.scale_x() * center);
+    }
+
+    pub fn set_center_y(&mut self, y: f32) {
+        let center = self.bottom + self.height / 2.0;
+        self.matrix
+            .set_translate_y(y - self.matrix.scale_y() * center);
+    }
+
+    /// キャンバスの左端をxに置く
+    pub fn set_left(&mut self, x: f32) {
+        self.matrix
+            .set_translate_x(x - self.matrix.scale_x() * self.left);
+    }
+
+    /// キャンバスの右端をxに置く
+    pub fn set_right(&mut self, x: f32) {
+        self.matrix
+            .set_translate_x(x - self.matrix.scale_x() * (self.left + self.width));
+    }
+
+    /// キャンバスの上端をyに置く
+    pub fn set_top(&mut self, y: f32) {
+        self.matrix
+            .set_translate_y(y - self.matrix.scale_y() * (self.bottom + self.height));
+    }
+
+    /// キャンバスの下端をyに置く
+    pub fn set_bottom(&mut self, y: f32) {
+        self.matrix
+            .set_translate_y(y - self.matrix.scale_y() * self.bottom);
+    }
+
+    /// キャンバスの中心を軸に左右を反転する
+    pub fn set_flip_x(&mut self, flip: bool) {
+        let center = self.matrix.transform([self.left + self.width / 2.0, 0.0])[0];
+        let scale = self.matrix.scale_x().abs();
+        self.matrix
+            .scale(if flip { -scale } else { scale }, self.matrix.scale_y());
+        self.set_center_x(center);
+    }
+
+    /// キャンバスの中心を軸に上下を反転する
+    pub fn set_flip_y(&mut self, flip: bool) {
+        let center = self
+            .matrix
+            .transform([0.0, self.bottom + self.height / 2.0])[1];
+        let scale = self.matrix.scale_y().abs();
+        self.matrix
+            .scale(self.matrix.scale_x(), if flip { -scale } else { scale });
+        self.set_center_y(center);
+    }
+
+    /// モデルの座標からビューの座標にする
+    #[inline]
+    pub fn model_to_view(&self, position: [f32; 2]) -> [f32; 2] {
+        self.matrix.transform(position)
+    }
+
+    /// ビューの座標からモデルの座標にする
+    #[inline]
+    pub fn view_to_model(&self, position: [f32; 2]) -> [f32; 2] {
+        self.matrix.invert_transform(position)
+    }
+}
+
+/// ビューの拡大と移動を範囲内に制限する行列
+/// 画面のピクセル座標との変換も持つ
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Math/CubismViewMatrix.cpp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewMatrix {
+    matrix: Matrix44,
+    /// ピクセル座標から画面の座標への変換
+    device_to_screen: Matrix44,
+    /// 画面に表示する範囲 [left, right, bottom, top]
+    screen_rect: [f32; 4],
+    /// 移動できる範囲 [left, right, bottom, top]
+    max_screen_rect: [f32; 4],
+    max_scale: f32,
+    min_scale: f32,
+}
+
+impl ViewMatrix {
+    /// width x heightのピクセルの画面に合わせる 公式のサンプルと同じ設定
+    /// 短い辺を-1.0から1.0にして、拡大は0.8倍から2.0倍まで
+    pub fn from_viewport(width: f32, height: f32) -> Self {
+        let mut view_matrix = ViewMatrix {
+            matrix: Matrix44::IDENTITY,
+            device_to_screen: Matrix44::IDENTITY,
+            screen_rect: [-1.0, 1.0, -1.0, 1.0],
+            max_screen_rect: [-2.0, 2.0, -2.0, 2.0],
+            max_scale: 2.0,
+            min_scale: 0.8,
+        };
+        view_matrix.set_viewport(width, height);
+        view_matrix
+    }
+
+    /// 画面の大きさが変わったときに呼ぶ 拡大と移動はそのまま
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+
+        // 短い辺が-1.0から1.0になるように長い辺を伸ばす
+        let unit = width.min(height) / 2.0;
+        let half_width = width / 2.0 / unit;
+        let half_height = height / 2.0 / unit;
+        self.screen_rect = [-half_width, half_width, -half_height, half_height];
+
+        self.device_to_screen = Matrix44::translation(-width / 2.0, -height / 2.0);
+        self.device_to_screen
+            .scale_relative(1.0 / unit, -1.0 / unit);
+    }
+
+    #[inline]
+    pub fn matrix(&self) -> &Matrix44 {
+        &self.matrix
+    }
+
+    #[inline]
+    pub fn device_to_screen(&self) -> &Matrix44 {
+        &self.device_to_screen
+    }
+
+    #[inline]
+    pub fn screen_rect(&self) -> [f32; 4] {
+        self.screen_rect
+    }
+
+    /// 画面に表示する範囲を設定する
+    pub fn set_screen_rect(&mut self, left: f32, right: f32, bottom: f32, top: f32) {
+        self.screen_rect = [left, right, bottom, top];
+    }
+
+    #[inline]
+    pub fn max_screen_rect(&self) -> [f32; 4] {
+        self.max_screen_rect
+    }
+
+    /// 移動できる範囲を設定する
+    pub fn set_max_screen_rect(&mut self, left: f32, right: f32, bottom: f32, top: f32) {
+        self.max_screen_rect = [left, right, bottom, top];
+    }
+
+    #[inline]
+    pub fn max_scale(&self) -> f32 {
+        self.max_scale
+    }
+
+    pub fn set_max_scale(&mut self, max_scale: f32) {
+        self.max_scale = max_scale;
+    }
+
+    #[inline]
+    pub fn min_scale(&self) -> f32 {
+        self.min_scale
+    }
+
+    pub fn set_min_scale(&mut self, min_scale: f32) {
+        self.min_scale = min_scale;
+    }
+
+    pub fn is_max_scale(&self) -> bool {
+        self.matrix.scale_x() >= self.max_scale
+    }
+
+    pub fn is_min_scale(&self) -> bool {
+        self.matrix.scale_x() <= self.min_scale
+    }
+
+    /// 移動できる範囲の外が画面に映らないように制限して平行移動する
+    pub fn adjust_translate(&mut self, x: f32, y: f32) {
+        let [screen_left, screen_right, screen_bottom, screen_top] = self.screen_rect;
+        let [max_left, max_right, max_bottom, max_top] = self.max_screen_rect;
+        let (scale_x, scale_y) = (self.matrix.scale_x(), self.matrix.scale_y());
+        let (translate_x, translate_y) = (self.matrix.translate_x(), self.matrix.translate_y());
+
+        let mut x = x;
+        if scale_x * max_left + translate_x + x > screen_left {
+            x = screen_left - scale_x * max_left - translate_x;
+        }
+        if scale_x * max_right + translate_x + x < screen_right {
+            x = screen_right - scale_x * max_right - translate_x;
+        }
+
+        let mut y = y;
+        if scale_y * max_top + translate_y + y < screen_top {
+            y = screen_top - scale_y * max_top - translate_y;
+        }
+        if scale_y * max_bottom + translate_y + y > screen_bottom {
+            y = screen_bottom - scale_y * max_bottom - translate_y;
+        }
+
+        self.matrix.translate_relative(x, y);
+    }
+
+    /// 画面の座標center_x, center_yを中心に拡大する
+    /// 拡大率はmin_scaleからmax_scaleまでに制限する
+    pub fn adjust_scale(&mut self, center_x: f32, center_y: f32, scale: f32) {
+        let current = self.matrix.scale_x();
+        let target = scale * current;
+        let scale = if current <= 0.0 {
+            scale
+        } else if target < self.min_scale {
+            self.min_scale / current
+        } else if target > self.max_scale {
+            self.max_scale / current
+        } else {
+            scale
+        };
+
+        self.matrix.translate_relative(-center_x, -center_y);
+        self.matrix.scale_relative(scale, scale);
+        self.matrix.translate_relative(center_x, center_y);
+    }
+
+    /// ピクセル座標(左上が原点、y軸が下向き)をビューの座標にする
+    pub fn device_to_view(&self, position: [f32; 2]) -> [f32; 2] {
+        self.matrix
+            .invert_transform(self.device_to_screen.transform(position))
+    }
+
+    /// ビューの座標をピクセル座標にする
+    pub fn view_to_device(&self, position: [f32; 2]) -> [f32; 2] {
+        self.device_to_screen
+            .invert_transform(self.matrix.transform(position))
+    }
+}
+
+impl Live2DCanvasInfo {
+    /// キャンバスの中心を原点に置いて高さを2.0にしたモデル行列
+    pub fn model_matrix(&self) -> ModelMatrix {
+        ModelMatrix::from_canvas_info(self)
+    }
+
+    /// width x heightの画面に合わせた射影行列を返す 公式のサンプルと同じ
+    /// 縦長の画面に横長のモデルを表示する場合はmodel_matrixを幅に合わせる
+    /// 描画には 射影行列 * ビュー行列 * モデル行列 を使う
+    pub fn fit_viewport(
+        &self,
+        model_matrix: &mut ModelMatrix,
+        width: f32,
+        height: f32,
+    ) -> Matrix44 {
+        if width <= 0.0 || height <= 0.0 {
+            return Matrix44::IDENTITY;
+        }
+
+        if self.get_canvas_with() > 1.0 && width < height {
+            model_matrix.set_width(2.0);
+            Matrix44::scaling(1.0, width / height)
+        } else {
+            Matrix44::scaling(height / width, 1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: [f32; 2], expected: [f32; 2]) {
+        assert!(
+            (actual[0] - expected[0]).abs() < 1e-5 && (actual[1] - expected[1]).abs() < 1e-5,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn matrix_test() {
+        let mut matrix = Matrix44::scaling(2.0, 3.0);
+        matrix.translate_relative(1.0, -1.0);
+        assert_near(matrix.transform([1.0, 1.0]), [3.0, 2.0]);
+        assert_near(matrix.invert_transform([3.0, 2.0]), [1.0, 1.0]);
+
+        // otherを先に変換する
+        let translation = Matrix44::translation(1.0, 0.0);
+        let scaling = Matrix44::scaling(2.0, 2.0);
+        assert_near(
+            scaling.multiply(&translation).transform([0.0, 0.0]),
+            [2.0, 0.0],
+        );
+        assert_near(
+            translation.multiply(&scaling).transform([0.0, 0.0]),
+            [1.0, 0.0],
+        );
+        assert_eq!(Matrix44::IDENTITY.multiply(&matrix), matrix);
+        assert_eq!(matrix.to_transform(), [2.0, 0.0, 0.0, 3.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn model_matrix_test() {
+        // 左下が原点の4 x 2のキャンバス
+        let mut model_matrix = ModelMatrix::with_canvas_rect(0.0, 0.0, 4.0, 2.0);
+        assert_eq!(model_matrix.matrix().scale_x(), 1.0);
+
+        model_matrix.set_width(2.0);
+        model_matrix.set_center_position(0.0, 0.0);
+        assert_near(model_matrix.model_to_view([0.0, 0.0]), [-1.0, -0.5]);
+        assert_near(model_matrix.model_to_view([4.0, 2.0]), [1.0, 0.5]);
+
+        model_matrix.set_top(1.0);
+        model_matrix.set_right(3.0);
+        assert_near(model_matrix.model_to_view([4.0, 2.0]), [3.0, 1.0]);
+        assert_near(model_matrix.view_to_model([3.0, 1.0]), [4.0, 2.0]);
+
+        // 反転しても位置は変わらない
+        model_matrix.set_flip_x(true);
+        assert_near(model_matrix.model_to_view([0.0, 2.0]), [3.0, 1.0]);
+        model_matrix.set_height(2.0);
+        assert!(model_matrix.matrix().scale_x() < 0.0);
+    }
+
+    #[test]
+    fn view_matrix_test() {
+        let mut view_matrix = ViewMatrix::from_viewport(400.0, 200.0);
+        assert_eq!(view_matrix.screen_rect(), [-2.0, 2.0, -1.0, 1.0]);
+        assert_near(view_matrix.device_to_view([0.0, 0.0]), [-2.0, 1.0]);
+        assert_near(view_matrix.device_to_view([200.0, 200.0]), [0.0, -1.0]);
+        assert_near(view_matrix.view_to_device([0.0, -1.0]), [200.0, 200.0]);
+
+        view_matrix.adjust_scale(1.0, 0.0, 10.0);
+        assert!(view_matrix.is_max_scale());
+        assert_near(view_matrix.matrix().transform([1.0, 0.0]), [1.0, 0.0]);
+
+        // 移動できる範囲の外は映さない
+        view_matrix.adjust_translate(100.0, 0.0);
+        let left = view_matrix.matrix().transform([-2.0, 0.0])[0];
+        assert!((left - view_matrix.screen_rect()[0]).abs() < 1e-5);
+    }
+}
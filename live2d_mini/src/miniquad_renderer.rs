@@ -0,0 +1,481 @@
+use image::EncodableLayout;
+use miniquad::*;
+
+use crate::clipping::{ClippingContext, DEFAULT_MASK_SIZE, MASK_CHANNEL_COUNT};
+use crate::draw_list::{BlendMode, DrawCommand, DrawList, Transform};
+use crate::matrix::Matrix44;
+use crate::model::Live2DModel;
+use crate::model_resource::Live2DModelResource;
+
+/// drawable一つ分のバッファ
+/// vertex_buffersは[頂点座標, uv座標]の順
+/// uvとインデックスは変わらないので、頂点座標だけ変わったときに書き換える
+#[derive(Debug, Clone)]
+struct MeshBindings {
+    bindings: Bindings,
+    index_count: usize,
+}
+
+/// miniquadでモデルを描画する
+/// updateでマスクを描画してから、描画パスの中でdrawを呼ぶ
+#[derive(Debug)]
+pub struct MiniquadRenderer {
+    textures: Vec<Texture>,
+    /// drawableの番号ごとのバッファ 頂点のないdrawableはNone
+    meshes: Vec<Option<MeshBindings>>,
+    /// BlendModeとカリングの組み合わせごとのパイプライン
+    pipelines: [[Pipeline; 2]; 3],
+    mask_pipeline: Pipeline,
+    mask_pass: RenderPass,
+    mask_texture: Texture,
+    draw_list: DrawList,
+}
+
+impl MiniquadRenderer {
+    /// モデルのテクスチャとdrawableのバッファを作る
+    pub fn new(ctx: &mut Context, model: &Live2DModel) -> Self {
+        Self::with_mask_size(ctx, model, DEFAULT_MASK_SIZE)
+    }
+
+    /// マスクのアトラスの一辺のピクセル数を指定して作る
+    pub fn with_mask_size(ctx: &mut Context, model: &Live2DModel, mask_size: u32) -> Self {
+        let textures = model
+            .textures
+            .iter()
+            .map(|texture| {
+                Texture::from_rgba8(
+                    ctx,
+                    texture.width() as _,
+                    texture.height() as _,
+                    texture.as_bytes(),
+                )
+            })
+            .collect::<Vec<Texture>>();
+
+        let mask_texture = Texture::new_render_texture(
+            ctx,
+            TextureParams {
+                format: TextureFormat::RGBA8,
+                wrap: TextureWrap::Clamp,
+                filter: FilterMode::Linear,
+                width: mask_size,
+                height: mask_size,
+            },
+        );
+        let mask_pass = RenderPass::new(ctx, mask_texture, None);
+
+        let draw_list = DrawList::from_resource(&model.resource);
+        let meshes = draw_list
+            .meshes
+            .iter()
+            .enumerate()
+            .map(|(index, mesh)| {
+                let indices = draw_list.mesh_indices(index);
+                let texture = textures.get(mesh.texture_index)?;
+                if indices.is_empty() {
+                    return None;
+                }
+
+                let position_buffer = Buffer::stream(
+                    ctx,
+                    BufferType::VertexBuffer,
+                    mesh.vertex_range.len() * std::mem::size_of::<[f32; 2]>(),
+                );
+                let uv_buffer =
+                    Buffer::immutable(ctx, BufferType::VertexBuffer, draw_list.mesh_uvs(index));
+                Some(MeshBindings {
+                    bindings: Bindings {
+                        vertex_buffers: vec![position_buffer, uv_buffer],
+                        index_buffer: Buffer::immutable(ctx, BufferType::IndexBuffer, indices),
+                        images: vec![*texture, mask_texture],
+                    },
+                    index_count: indices.len(),
+                })
+            })
+            .collect();
+
+        let renderer = MiniquadRenderer {
+            textures,
+            meshes,
+            pipelines: create_pipelines(ctx),
+            mask_pipeline: create_mask_pipeline(ctx),
+            mask_pass,
+            mask_texture,
+            draw_list,
+        };
+        renderer.upload_vertices(ctx);
+
+        renderer
+    }
+
+    #[inline]
+    pub fn textures(&self) -> &[Texture] {
+        &self.textures
+    }
+
+    #[inline]
+    pub fn mask_texture(&self) -> Texture {
+        self.mask_texture
+    }
+
+    #[inline]
+    pub fn draw_list(&self) -> &DrawList {
+        &self.draw_list
+    }
+
+    /// モデルの変わったところを読み込んでマスクを描画する
+    /// 描画パスの外で、モデルを更新するたびに呼ぶ
+    pub fn update(&mut self, ctx: &mut Context, resource: &Live2DModelResource) {
+        self.draw_list.update(resource);
+        self.upload_vertices(ctx);
+        self.render_masks(ctx);
+    }
+
+    /// 描画する
+    /// projectionはモデルの座標からクリップ座標への変換
+    pub fn draw(&self, ctx: &mut Context, projection: &Matrix44) {
+        for command in self.draw_list.commands.iter() {
+            let mesh = match self.meshes.get(command.drawable_index) {
+                Some(Some(mesh)) => mesh,
+                _ => continue,
+            };
+
+            ctx.apply_pipeline(&self.pipeline(command));
+            ctx.apply_bindings(&mesh.bindings);
+            ctx.apply_uniforms(&self.draw_uniforms(command, projection));
+            ctx.draw(0, mesh.index_count as _, 1);
+        }
+    }
+
+    /// GPUのリソースを解放する
+    pub fn delete(&mut self, ctx: &mut Context) {
+        for mesh in self.meshes.drain(..).flatten() {
+            mesh.bindings.index_buffer.delete();
+            for buffer in mesh.bindings.vertex_buffers.iter() {
+                buffer.delete();
+            }
+        }
+        for texture in self.textures.drain(..) {
+            texture.delete();
+        }
+        // マスクのテクスチャも一緒に解放される
+        self.mask_pass.delete(ctx);
+    }
+
+    fn pipeline(&self, command: &DrawCommand) -> Pipeline {
+        let blend = match command.blend_mode {
+            BlendMode::Normal => 0,
+            BlendMode::Additive => 1,
+            BlendMode::Multiplicative => 2,
+        };
+
+        self.pipelines[blend][usize::from(command.culling)]
+    }
+
+    fn draw_uniforms(&self, command: &DrawCommand, projection: &Matrix44) -> shader::Uniforms {
+        let context = self.draw_list.clipping_context(command);
+        let multiply = command.multiply_color;
+        let screen = command.screen_color;
+
+        shader::Uniforms {
+            projection: *projection.as_array(),
+            clip_matrix: context
+                .map(|context| transform_matrix(&context.transform))
+                .unwrap_or_default(),
+            base_color: [1.0, 1.0, 1.0, command.opacity],
+            multiply_color: [multiply.r, multiply.g, multiply.b, multiply.a],
+            screen_color: [screen.r, screen.g, screen.b, screen.a],
+            channel_flag: context.map(channel_flag).unwrap_or_default(),
+            mask_flag: [
+                if context.is_some() { 1.0 } else { 0.0 },
+                if command.inverted_mask { 1.0 } else { 0.0 },
+                0.0,
+                0.0,
+            ],
+        }
+    }
+
+    /// 全てのマスクをアトラスに描画する
+    fn render_masks(&self, ctx: &mut Context) {
+        if self.draw_list.clipping_contexts.is_empty() {
+            return;
+        }
+
+        ctx.begin_pass(self.mask_pass, PassAction::clear_color(0.0, 0.0, 0.0, 0.0));
+        ctx.apply_pipeline(&self.mask_pipeline);
+
+        for context in self.draw_list.clipping_contexts.iter() {
+            // アトラスの座標(0.0から1.0)をクリップ座標(-1.0から1.0)にする
+            let transform = context.transform.map(|value| value * 2.0);
+            let mut matrix = transform_matrix(&transform);
+            matrix[12] -= 1.0;
+            matrix[13] -= 1.0;
+
+            let uniforms = shader::MaskUniforms {
+                mask_matrix: matrix,
+                channel_flag: channel_flag(context),
+                layout: context.layout,
+            };
+
+            for mask_index in context.masks.iter() {
+                let mesh = match self.meshes.get(*mask_index) {
+                    Some(Some(mesh)) => mesh,
+                    _ => continue,
+                };
+
+                ctx.apply_bindings(&mesh.bindings);
+                ctx.apply_uniforms(&uniforms);
+                ctx.draw(0, mesh.index_count as _, 1);
+            }
+        }
+
+        ctx.end_render_pass();
+    }
+
+    /// 頂点座標が変わったdrawableだけバッファに書き込む
+    fn upload_vertices(&self, ctx: &mut Context) {
+        for index in self.draw_list.changed_meshes.iter() {
+            if let Some(Some(mesh)) = self.meshes.get(*index) {
+                mesh.bindings.vertex_buffers[0].update(ctx, self.draw_list.mesh_positions(*index));
+            }
+        }
+    }
+}
+
+/// 2次元のアフィン変換を列優先の4x4行列にする
+pub fn transform_matrix(transform: &Transform) -> [f32; 16] {
+    let [a, b, c, d, e, f] = *transform;
+
+    [
+        a, b, 0.0, 0.0, //
+        c, d, 0.0, 0.0, //
+        0.0, 0.0, 1.0, 0.0, //
+        e, f, 0.0, 1.0,
+    ]
+}
+
+/// contextのチャンネルだけ1.0にする
+fn channel_flag(context: &ClippingContext) -> [f32; MASK_CHANNEL_COUNT] {
+    let mut flag = [0.0; MASK_CHANNEL_COUNT];
+    flag[context.channel] = 1.0;
+    flag
+}
+
+/// 乗算済みアルファで合成する
+/// 加算と乗算はアルファを変えない
+fn blend_states(blend_mode: BlendMode) -> (BlendState, BlendState) {
+    let keep_alpha = BlendState::new(Equation::Add, BlendFactor::Zero, BlendFactor::One);
+
+    match blend_mode {
+        BlendMode::Normal => {
+            let state = BlendState::new(
+                Equation::Add,
+                BlendFactor::One,
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            );
+            (state, state)
+        }
+        BlendMode::Additive => (
+            BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One),
+            keep_alpha,
+        ),
+        BlendMode::Multiplicative => (
+            BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::DestinationColor),
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            ),
+            keep_alpha,
+        ),
+    }
+}
+
+/// 頂点座標とuv座標は別のバッファから読む
+fn buffer_layouts() -> [BufferLayout; 2] {
+    [BufferLayout::default(), BufferLayout::default()]
+}
+
+fn vertex_attributes() -> [VertexAttribute; 2] {
+    [
+        VertexAttribute::with_buffer("pos", VertexFormat::Float2, 0),
+        VertexAttribute::with_buffer("uv", VertexFormat::Float2, 1),
+    ]
+}
+
+/// [BlendMode][カリングするか]の順に並べる
+fn create_pipelines(ctx: &mut Context) -> [[Pipeline; 2]; 3] {
+    let shader = Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta())
+        .expect("failed to compile the model shader");
+
+    [
+        BlendMode::Normal,
+        BlendMode::Additive,
+        BlendMode::Multiplicative,
+    ]
+    .map(|blend_mode| {
+        let (color_blend, alpha_blend) = blend_states(blend_mode);
+
+        // 両面描画のdrawableだけカリングしない
+        [CullFace::Nothing, CullFace::Back].map(|cull_face| {
+            Pipeline::with_params(
+                ctx,
+                &buffer_layouts(),
+                &vertex_attributes(),
+                shader,
+                PipelineParams {
+                    cull_face,
+                    front_face_order: FrontFaceOrder::CounterClockwise,
+                    color_blend: Some(color_blend),
+                    alpha_blend: Some(alpha_blend),
+                    ..Default::default()
+                },
+            )
+        })
+    })
+}
+
+fn create_mask_pipeline(ctx: &mut Context) -> Pipeline {
+    let shader = Shader::new(
+        ctx,
+        shader::MASK_VERTEX,
+        shader::MASK_FRAGMENT,
+        shader::mask_meta(),
+    )
+    .expect("failed to compile the mask shader");
+
+    // 同じチャンネルのマスクはアルファを重ねる
+    let state = BlendState::new(
+        Equation::Add,
+        BlendFactor::One,
+        BlendFactor::OneMinusValue(BlendValue::SourceColor),
+    );
+
+    Pipeline::with_params(
+        ctx,
+        &buffer_layouts(),
+        &vertex_attributes(),
+        shader,
+        PipelineParams {
+            color_blend: Some(state),
+            alpha_blend: Some(state),
+            ..Default::default()
+        },
+    )
+}
+
+mod shader {
+    use miniquad::*;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+    attribute vec2 uv;
+    uniform mat4 projection;
+    uniform mat4 clip_matrix;
+    varying lowp vec2 texcoord;
+    varying highp vec4 clip_pos;
+    void main() {
+        gl_Position = projection * vec4(pos, 0, 1);
+        texcoord = uv;
+        clip_pos = clip_matrix * vec4(pos, 0, 1);
+    }"#;
+
+    pub const FRAGMENT: &str = r#"#version 100
+    precision mediump float;
+    varying lowp vec2 texcoord;
+    varying highp vec4 clip_pos;
+    uniform sampler2D tex;
+    uniform sampler2D mask;
+    uniform vec4 base_color;
+    uniform vec4 multiply_color;
+    uniform vec4 screen_color;
+    uniform vec4 channel_flag;
+    uniform vec4 mask_flag;
+    void main() {
+        vec4 color = texture2D(tex, texcoord);
+        color.rgb *= multiply_color.rgb;
+        color.rgb = color.rgb + screen_color.rgb - color.rgb * screen_color.rgb;
+        color.a *= base_color.a;
+        if (mask_flag.x > 0.5) {
+            float value = dot(texture2D(mask, clip_pos.xy / clip_pos.w), channel_flag);
+            color.a *= mix(value, 1.0 - value, mask_flag.y);
+        }
+        gl_FragColor = vec4(color.rgb * color.a, color.a);
+    }"#;
+
+    /// フィールドの順番はmetaのuniformsと同じにする
+    #[repr(C)]
+    pub struct Uniforms {
+        pub projection: [f32; 16],
+        pub clip_matrix: [f32; 16],
+        pub base_color: [f32; 4],
+        pub multiply_color: [f32; 4],
+        pub screen_color: [f32; 4],
+        pub channel_flag: [f32; 4],
+        /// xはマスクがあるか、yは反転マスクか
+        pub mask_flag: [f32; 4],
+    }
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string(), "mask".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("projection", UniformType::Mat4),
+                    UniformDesc::new("clip_matrix", UniformType::Mat4),
+                    UniformDesc::new("base_color", UniformType::Float4),
+                    UniformDesc::new("multiply_color", UniformType::Float4),
+                    UniformDesc::new("screen_color", UniformType::Float4),
+                    UniformDesc::new("channel_flag", UniformType::Float4),
+                    UniformDesc::new("mask_flag", UniformType::Float4),
+                ],
+            },
+        }
+    }
+
+    pub const MASK_VERTEX: &str = r#"#version 100
+    attribute vec2 pos;
+    attribute vec2 uv;
+    uniform mat4 mask_matrix;
+    varying lowp vec2 texcoord;
+    varying highp vec2 atlas_pos;
+    void main() {
+        gl_Position = mask_matrix * vec4(pos, 0, 1);
+        texcoord = uv;
+        atlas_pos = (gl_Position.xy / gl_Position.w + 1.0) * 0.5;
+    }"#;
+
+    pub const MASK_FRAGMENT: &str = r#"#version 100
+    precision mediump float;
+    varying lowp vec2 texcoord;
+    varying highp vec2 atlas_pos;
+    uniform sampler2D tex;
+    uniform vec4 channel_flag;
+    uniform vec4 layout;
+    void main() {
+        // 他のレイアウトにはみ出さない
+        float inside = step(layout.x, atlas_pos.x) * step(layout.y, atlas_pos.y)
+            * step(atlas_pos.x, layout.x + layout.z) * step(atlas_pos.y, layout.y + layout.w);
+        gl_FragColor = channel_flag * texture2D(tex, texcoord).a * inside;
+    }"#;
+
+    #[repr(C)]
+    pub struct MaskUniforms {
+        pub mask_matrix: [f32; 16],
+        pub channel_flag: [f32; 4],
+        /// アトラス内の範囲 [x, y, width, height]
+        pub layout: [f32; 4],
+    }
+
+    pub fn mask_meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("mask_matrix", UniformType::Mat4),
+                    UniformDesc::new("channel_flag", UniformType::Float4),
+                    UniformDesc::new("layout", UniformType::Float4),
+                ],
+            },
+        }
+    }
+}
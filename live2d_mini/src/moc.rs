@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::address::Live2DAddress;
+use crate::asset::{AssetSource, FileSource};
+use crate::error::Live2DLoadError;
+use crate::model_resource::Live2DModelResource;
+
+/// 読み込んで復元したmoc3
+/// 一つのmocから複数のモデルを生成でき、モデルが残っている間は解放されない
+#[derive(Debug, PartialEq)]
+pub struct Live2DMoc {
+    /// 読み込んだmoc3ファイル エラーの表示に使う
+    path: PathBuf,
+    /// このアドレスを破棄の対象にする
+    _address: Live2DAddress,
+
+    moc: *mut live2d_mini_sys::csmMoc,
+}
+
+impl Live2DMoc {
+    /// moc3ファイルを読み込む
+    pub fn new<P>(path: P) -> Result<Rc<Self>, Live2DLoadError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::load(&FileSource::default(), path)
+    }
+
+    /// AssetSourceからmoc3ファイルを読み込む
+    pub fn load<S, P>(source: &S, path: P) -> Result<Rc<Self>, Live2DLoadError>
+    where
+        S: AssetSource + ?Sized,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let bytes = source.read(path).map_err(|source| Live2DLoadError::Moc {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::with_path(path.to_path_buf(), &bytes)
+    }
+
+    /// メモリ上のmoc3を読み込む
+    /// 整列されたバッファにコピーしてから復元する
+    pub fn from_bytes(bytes: &[u8]) -> Result<Rc<Self>, Live2DLoadError> {
+        Self::with_path(PathBuf::new(), bytes)
+    }
+
+    fn with_path(path: PathBuf, bytes: &[u8]) -> Result<Rc<Self>, Live2DLoadError> {
+        let address = Self::allocate(&path, bytes.len())?;
+        let moc_slice = unsafe { std::slice::from_raw_parts_mut(address.ptr, bytes.len()) };
+        moc_slice.copy_from_slice(bytes);
+
+        Self::revive(path, address, bytes.len())
+    }
+
+    fn allocate(path: &Path, size: usize) -> Result<Live2DAddress, Live2DLoadError> {
+        Live2DAddress::allocate_zeroed(size, live2d_mini_sys::csmAlignofMoc as _).map_err(
+            |source| Live2DLoadError::MocAllocation {
+                path: path.to_path_buf(),
+                source,
+            },
+        )
+    }
+
+    /// 読み込んだmoc3を確認して復元する
+    fn revive(
+        path: PathBuf,
+        address: Live2DAddress,
+        size: usize,
+    ) -> Result<Rc<Self>, Live2DLoadError> {
+        unsafe {
+            // moc3ファイルの整合性を確認する
+            if live2d_mini_sys::csmHasMocConsistency(address.ptr as _, size as _) == 0 {
+                return Err(Live2DLoadError::MocConsistency { path });
+            }
+
+            let moc = live2d_mini_sys::csmReviveMocInPlace(address.ptr as _, size as _);
+            if moc.is_null() {
+                return Err(Live2DLoadError::MocRevive { path });
+            }
+
+            Ok(Rc::new(Live2DMoc {
+                path,
+                _address: address,
+                moc,
+            }))
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// このmocから独立したパラメータを持つモデルを生成する
+    pub fn create_resource(self: &Rc<Self>) -> Result<Live2DModelResource, Live2DLoadError> {
+        Live2DModelResource::from_moc(Rc::clone(self))
+    }
+
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *const live2d_mini_sys::csmMoc {
+        self.moc
+    }
+}
@@ -1,16 +1,31 @@
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufReader, Cursor, Read, Seek};
 use std::path::Path;
 
 use crate::animation::*;
+use crate::asset::{AssetSource, FileSource, ZipSource};
+use crate::breath::Breath;
+use crate::error::{Live2DFileError, Live2DLoadError};
+use crate::expression::{Expression, ExpressionManager};
+use crate::expression_json;
+use crate::eye_blink::EyeBlink;
+use crate::hit_test::{self, HitArea, HitTestMode};
+use crate::lip_sync::LipSync;
 use crate::model_json;
 use crate::motion_json;
 use crate::physic_json;
+use crate::pose::Pose;
+use crate::pose_json;
 
 use image::RgbaImage;
 
+use crate::moc::Live2DMoc;
 use crate::model_resource::Live2DModelResource;
+use crate::motion_manager::{FinishedMotion, MotionManager, MotionPriority, MotionRequest};
+use crate::motion_queue::MotionHandle;
 use crate::physic::Physics;
+use crate::target_point::DragController;
 
 #[derive(Debug)]
 pub struct Live2DModel {
@@ -18,52 +33,158 @@ pub struct Live2DModel {
     pub animations: Vec<Animation>,
     pub textures: Vec<RgbaImage>,
     pub physics: Option<Physics>,
+    pub expressions: Vec<Expression>,
+    pub pose: Option<Pose>,
+    pub eye_blink: Option<EyeBlink>,
+    pub lip_sync: Option<LipSync>,
+    pub breath: Option<Breath>,
+    pub drag: Option<DragController>,
+    /// model3.jsonのHitAreasの順番
+    pub hit_areas: Vec<HitArea>,
 
+    /// モーショングループ名とそのグループに属するanimationsの番号
+    motion_groups: BTreeMap<String, Vec<usize>>,
     /// 再生するアニメーションの番号
     animation_index: Option<usize>,
+    /// 再生中のモーション
+    motion_manager: MotionManager,
+    /// 再生中の表情
+    expression_manager: ExpressionManager,
+    /// モーション適用後のパラメータ
+    /// 毎フレームここから読み込んでからモーションを適用する
+    saved_parameters: Vec<f32>,
+    hit_test_mode: HitTestMode,
 }
 
 impl<'a> Live2DModel {
+    /// model3.jsonを読み込んでモデルを生成する
+    /// 読み込みに失敗した場合はpanicする
     pub fn new<P>(path: P) -> Self
     where
         P: AsRef<Path>,
     {
-        let path = Path::new(path.as_ref());
+        match Self::load(path) {
+            Ok(model) => model,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// model3.jsonを読み込んでモデルを生成する
+    pub fn load<P>(path: P) -> Result<Self, Live2DLoadError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        // zipファイルはアーカイブ内のmodel3.jsonを読み込む
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+        {
+            return Self::load_zip(path);
+        }
+
+        Self::from_source(&FileSource::default(), path)
+    }
+
+    /// zipファイルに含まれるmodel3.jsonを読み込んでモデルを生成する
+    pub fn load_zip<P>(path: P) -> Result<Self, Live2DLoadError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|source| Live2DLoadError::Archive {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::from_zip(BufReader::new(file), path)
+    }
+
+    /// メモリ上のzipファイルに含まれるmodel3.jsonを読み込んでモデルを生成する
+    pub fn from_zip_bytes(bytes: &[u8]) -> Result<Self, Live2DLoadError> {
+        Self::from_zip(Cursor::new(bytes), Path::new(""))
+    }
+
+    fn from_zip<R>(reader: R, path: &Path) -> Result<Self, Live2DLoadError>
+    where
+        R: Read + Seek,
+    {
+        let archive_error = |source| Live2DLoadError::Archive {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let source = ZipSource::new(reader).map_err(archive_error)?;
+        let model_json_path = source.find_model_json().ok_or_else(|| {
+            archive_error(io::Error::new(
+                io::ErrorKind::NotFound,
+                "model3.json is not found",
+            ))
+        })?;
+
+        Self::from_source(&source, model_json_path)
+    }
 
-        let current_dir = path.parent().expect("cannot find parents");
+    /// AssetSourceからmodel3.jsonを読み込んでモデルを生成する
+    /// model3.jsonから参照されるファイルはmodel3.jsonのディレクトリからの相対パスで読み込む
+    pub fn from_source<S, P>(source: &S, path: P) -> Result<Self, Live2DLoadError>
+    where
+        S: AssetSource + ?Sized,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
 
-        let file = File::open(path).expect(&format!("cannot open file: {:?}", path.to_str()));
-        let reader = BufReader::new(file);
+        let current_dir = path.parent().unwrap_or_else(|| Path::new(""));
 
         let model_json: model_json::ModelJson =
-            serde_json::from_reader(reader).expect("deselialize error");
+            read_json(source, path).map_err(|source| Live2DLoadError::ModelJson {
+                path: path.to_path_buf(),
+                source,
+            })?;
 
         let textures = model_json
             .FileReferences
             .Textures
             .iter()
-            .map(|path| {
-                image::io::Reader::open(current_dir.join(path))
-                    .expect("not find image")
-                    .decode()
-                    .expect("decode faild")
-                    .flipv()
-                    .to_rgba8()
+            .enumerate()
+            .map(|(index, texture_path)| {
+                let texture_path = current_dir.join(texture_path);
+                source
+                    .read(&texture_path)
+                    .map_err(image::ImageError::IoError)
+                    .and_then(|bytes| image::load_from_memory(&bytes))
+                    .map(|image| image.flipv().to_rgba8())
+                    .map_err(|source| Live2DLoadError::Texture {
+                        index,
+                        path: texture_path,
+                        source,
+                    })
             })
-            .collect::<Vec<RgbaImage>>();
-
-        let resource = Live2DModelResource::new(current_dir.join(model_json.FileReferences.Moc))
-            .expect("moc load error");
-        // let file =
-        //     File::open(current_dir.join(model_json.FileReferences.Pose.expect(""))).expect("");
-        // let reader = BufReader::new(file);
-        // let u: pose_json::PoseJson = serde_json::from_reader(reader).expect("");
-
-        let physics = if let Some(physics_path) = model_json.FileReferences.Physics {
-            let file = File::open(current_dir.join(physics_path)).expect("open error");
-            let reader = BufReader::new(file);
+            .collect::<Result<Vec<RgbaImage>, Live2DLoadError>>()?;
+
+        let moc = Live2DMoc::load(source, current_dir.join(&model_json.FileReferences.Moc))?;
+        let mut resource = Live2DModelResource::from_moc(moc)?;
+        let pose = if let Some(pose_path) = &model_json.FileReferences.Pose {
+            let pose_path = current_dir.join(pose_path);
+            let pose_json: pose_json::PoseJson =
+                read_json(source, &pose_path).map_err(|source| Live2DLoadError::Pose {
+                    path: pose_path,
+                    source,
+                })?;
+            let pose = Pose::new(&pose_json, &resource);
+            pose.reset(&mut resource);
+            Some(pose)
+        } else {
+            None
+        };
+
+        let physics = if let Some(physics_path) = &model_json.FileReferences.Physics {
+            let physics_path = current_dir.join(physics_path);
             let physic_json: physic_json::PhysicJson =
-                serde_json::from_reader(reader).expect("load error");
+                read_json(source, &physics_path).map_err(|source| Live2DLoadError::Physics {
+                    path: physics_path,
+                    source,
+                })?;
             let mut raw_physics = Physics::new(physic_json);
             raw_physics.initialize();
             Some(raw_physics)
@@ -71,31 +192,110 @@ impl<'a> Live2DModel {
             None
         };
 
-        let motions = model_json
+        let eye_blink_ids = group_ids(&model_json, EYE_BLINK_GROUP);
+        let lip_sync_ids = group_ids(&model_json, LIP_SYNC_GROUP);
+
+        let mut animations = vec![];
+        let mut motion_groups = BTreeMap::new();
+        for (group, motions) in model_json.FileReferences.Motions.iter().flatten() {
+            let mut indices = vec![];
+            for motion in motions {
+                let motion_path = current_dir.join(&motion.File);
+                let mut animation = read_json(source, &motion_path)
+                    .and_then(|motion_json: motion_json::MotionJson| {
+                        Ok(Animation::new(&motion_json)?)
+                    })
+                    .map_err(|source| Live2DLoadError::Motion {
+                        path: motion_path,
+                        source,
+                    })?;
+
+                animation.eye_blink_parameter_ids = eye_blink_ids.clone();
+                animation.lip_sync_parameter_ids = lip_sync_ids.clone();
+                animation.bind(&resource);
+                if let Some(fade_in_time) = motion.FadeInTime {
+                    animation.fade_in_time = fade_in_time;
+                }
+                if let Some(fade_out_time) = motion.FadeOutTime {
+                    animation.fade_out_time = fade_out_time;
+                }
+
+                indices.push(animations.len());
+                animations.push(animation);
+            }
+            motion_groups.insert(group.clone(), indices);
+        }
+
+        let expressions = model_json
             .FileReferences
-            .Motions
-            .expect("")
-            .Idle
+            .Expressions
             .iter()
-            .map(|idle| {
-                let file = File::open(current_dir.join(&idle.File)).expect("file open error");
-                let reader = BufReader::new(file);
-                serde_json::from_reader(reader).expect("deselialize error")
+            .flatten()
+            .map(|expression| {
+                let expression_path = current_dir.join(&expression.File);
+                let expression_json: expression_json::ExpressionJson =
+                    read_json(source, &expression_path).map_err(|source| {
+                        Live2DLoadError::Expression {
+                            path: expression_path,
+                            source,
+                        }
+                    })?;
+
+                Ok(Expression::new(
+                    &expression.Name,
+                    &expression_json,
+                    &resource,
+                ))
             })
-            .collect::<Vec<motion_json::MotionJson>>();
+            .collect::<Result<Vec<Expression>, Live2DLoadError>>()?;
 
-        let animations = motions
+        let eye_blink = if eye_blink_ids.is_empty() {
+            None
+        } else {
+            Some(EyeBlink::new(eye_blink_ids, &resource))
+        };
+        let lip_sync = if lip_sync_ids.is_empty() {
+            None
+        } else {
+            Some(LipSync::new(lip_sync_ids, &resource))
+        };
+
+        let hit_areas = model_json
+            .HitAreas
             .iter()
-            .map(|motion| Animation::new(motion))
-            .collect::<Vec<Animation>>();
+            .flatten()
+            .map(|hit_area| HitArea::new(&hit_area.Name, &hit_area.Id, &resource))
+            .collect();
+
+        let breath = Breath::with_default_parameters(&resource);
+        let drag = DragController::with_default_parameters(&mut resource);
+
+        let saved_parameters = resource.csm_get_parameter_values().to_vec();
+
+        let mut motion_manager = MotionManager::new();
+        if motion_groups.contains_key(IDLE_GROUP) {
+            motion_manager.set_idle_group(Some(IDLE_GROUP.to_string()));
+        }
 
-        Live2DModel {
+        Ok(Live2DModel {
             resource,
             animations,
             textures,
             physics,
+            expressions,
+            pose,
+            eye_blink,
+            lip_sync,
+            breath: Some(breath),
+            drag: Some(drag),
+            hit_areas,
+            motion_groups,
             animation_index: None,
-        }
+            motion_manager,
+            expression_manager: ExpressionManager::new(),
+            saved_parameters,
+            hit_test_mode: HitTestMode::default(),
+        })
     }
 
     pub fn animation(&mut self, time: f32) {
@@ -125,23 +325,377 @@ impl<'a> Live2DModel {
         }
     }
 
-    /// indexを設定した値にし
-    /// 再生時間を0にする
+    /// 表示するパーツを切り替える
+    pub fn evaluate_pose(&mut self, delta_time: f32) {
+        if let Some(pose) = self.pose.as_ref() {
+            pose.update(&mut self.resource, delta_time)
+        }
+    }
+
+    /// モーショングループ名の一覧
+    pub fn motion_groups(&self) -> impl Iterator<Item = &str> {
+        self.motion_groups.keys().map(|group| group.as_str())
+    }
+
+    /// グループに含まれるモーションの数
+    pub fn motion_count(&self, group: &str) -> usize {
+        self.motion_groups
+            .get(group)
+            .map(|indices| indices.len())
+            .unwrap_or(0)
+    }
+
+    /// グループとグループ内の番号からanimationsの番号を取得する
+    pub fn motion_index(&self, group: &str, index: usize) -> Option<usize> {
+        self.motion_groups
+            .get(group)
+            .and_then(|indices| indices.get(index))
+            .copied()
+    }
+
+    pub fn get_motion(&self, group: &str, index: usize) -> Option<&Animation> {
+        self.motion_index(group, index)
+            .and_then(|index| self.animations.get(index))
+    }
+
+    /// グループとグループ内の番号を指定してモーションを再生する
+    /// 見つからない・再生できなければfalseを返す
+    pub fn start_motion(&mut self, group: &str, index: usize) -> bool {
+        self.start_motion_priority(group, index, MotionPriority::Normal)
+            .is_some()
+    }
+
+    /// 優先度を指定してモーションを再生する
+    /// 再生中のモーションより優先度が高くなければ再生しない
+    pub fn start_motion_priority(
+        &mut self,
+        group: &str,
+        index: usize,
+        priority: MotionPriority,
+    ) -> Option<MotionHandle> {
+        let animation_index = self.motion_index(group, index)?;
+        let handle = self.motion_manager.start_motion_priority(
+            animation_index,
+            Some(group),
+            index,
+            priority,
+            &mut self.animations,
+        )?;
+        self.animation_index = Some(animation_index);
+
+        Some(handle)
+    }
+
+    /// グループからランダムに選んだモーションを再生する
+    pub fn start_random_motion(
+        &mut self,
+        group: &str,
+        priority: MotionPriority,
+    ) -> Option<MotionHandle> {
+        let count = self.motion_count(group);
+        if count == 0 {
+            return None;
+        }
+
+        let index = self.motion_manager.random_index(count);
+        self.start_motion_priority(group, index, priority)
+    }
+
+    /// 再生中のモーションが終わったら再生するモーションを追加する
+    pub fn queue_motion(&mut self, group: &str, index: usize, priority: MotionPriority) {
+        self.motion_manager.queue_motion(MotionRequest {
+            group: group.to_string(),
+            index,
+            priority,
+        });
+    }
+
+    pub fn motion_manager(&self) -> &MotionManager {
+        &self.motion_manager
+    }
+
+    pub fn motion_manager_mut(&mut self) -> &mut MotionManager {
+        &mut self.motion_manager
+    }
+
+    /// 直前のupdateで再生が終わったモーション
+    pub fn finished_motions(&self) -> &[FinishedMotion] {
+        self.motion_manager.finished_motions()
+    }
+
+    /// indexのアニメーションを再生する
+    /// 再生中のアニメーションからはクロスフェードする
     pub fn reset_animation(&mut self, index: usize) {
         self.animation_index = Some(index);
-        self.animation(0.0);
+        self.motion_manager.start_motion_priority(
+            index,
+            None,
+            index,
+            MotionPriority::Force,
+            &mut self.animations,
+        );
+    }
+
+    /// 再生中のモーションを全て止めてパラメータを初期値に戻す
+    pub fn stop_all_motions(&mut self) {
+        self.motion_manager.stop_all_motions();
         self.replace_default_values();
+        self.save_parameters();
+    }
+
+    /// 全てのモーションの再生が終わったか
+    pub fn is_motion_finished(&self) -> bool {
+        self.motion_manager.is_finished()
+    }
+
+    /// 時間を進めて再生中のモーションをパラメータに適用する
+    /// 何も再生していなければ待機中のモーションかidleグループのモーションを再生する
+    /// モーションを適用した場合はtrueを返す
+    pub fn update_motion(&mut self, delta_time: f32) -> bool {
+        if self.motion_manager.is_finished() {
+            let motion_groups = &self.motion_groups;
+            if let Some(request) = self.motion_manager.next_request(|group| {
+                motion_groups
+                    .get(group)
+                    .map(|indices| indices.len())
+                    .unwrap_or(0)
+            }) {
+                self.start_motion_priority(&request.group, request.index, request.priority);
+            }
+        }
+
+        self.load_parameters();
+        let updated =
+            self.motion_manager
+                .update(&mut self.animations, &mut self.resource, delta_time);
+        self.save_parameters();
+
+        updated
+    }
+
+    /// 自動まばたきを有効・無効にする
+    pub fn set_eye_blink_enabled(&mut self, enabled: bool) {
+        if let Some(eye_blink) = self.eye_blink.as_mut() {
+            eye_blink.set_enabled(enabled);
+        }
+    }
+
+    /// 自動まばたきをパラメータに適用する
+    pub fn evaluate_eye_blink(&mut self, delta_time: f32) {
+        if let Some(eye_blink) = self.eye_blink.as_mut() {
+            eye_blink.update(&mut self.resource, delta_time);
+        }
+    }
+
+    /// 顔を向ける位置を設定する
+    /// 座標は-1.0から1.0に正規化したモデルの座標
+    pub fn set_dragging(&mut self, x: f32, y: f32) {
+        if let Some(drag) = self.drag.as_mut() {
+            drag.set_target(x, y);
+        }
+    }
+
+    /// 顔を向ける位置をパラメータに適用する
+    pub fn evaluate_drag(&mut self, delta_time: f32) {
+        if let Some(drag) = self.drag.as_mut() {
+            drag.update(&mut self.resource, delta_time);
+        }
+    }
+
+    /// 呼吸をパラメータに適用する
+    pub fn evaluate_breath(&mut self, delta_time: f32) {
+        if let Some(breath) = self.breath.as_mut() {
+            breath.update(&mut self.resource, delta_time);
+        }
+    }
+
+    /// 口パクの音量(RMS)を設定する
+    pub fn set_lip_sync_value(&mut self, rms: f32) {
+        if let Some(lip_sync) = self.lip_sync.as_mut() {
+            lip_sync.set_rms(rms);
+        }
+    }
+
+    /// 口パクをパラメータに適用する
+    pub fn evaluate_lip_sync(&mut self, delta_time: f32) {
+        if let Some(lip_sync) = self.lip_sync.as_mut() {
+            lip_sync.update(&mut self.resource, delta_time);
+        }
+    }
+
+    /// animationに口パクのパラメータを設定して追加する
+    /// 追加したanimationsの番号を返す
+    pub fn add_lip_sync_animation(&mut self, mut animation: Animation) -> usize {
+        animation.lip_sync_parameter_ids = self
+            .lip_sync
+            .as_ref()
+            .map(|lip_sync| lip_sync.parameter_ids().to_vec())
+            .unwrap_or_default();
+        animation.bind(&self.resource);
+        self.animations.push(animation);
+        self.animations.len() - 1
+    }
+
+    /// 表情の名前の一覧
+    pub fn expression_names(&self) -> impl Iterator<Item = &str> {
+        self.expressions
+            .iter()
+            .map(|expression| expression.name.as_str())
+    }
+
+    /// 名前を指定して表情を設定する
+    /// 見つからなければfalseを返す
+    pub fn set_expression(&mut self, name: &str) -> bool {
+        if let Some(index) = self
+            .expressions
+            .iter()
+            .position(|expression| expression.name == name)
+        {
+            self.expression_manager.start_expression(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// ランダムに選んだ表情を設定する
+    pub fn set_random_expression(&mut self) -> bool {
+        if self.expressions.is_empty() {
+            return false;
+        }
+
+        let index = self.motion_manager.random_index(self.expressions.len());
+        self.expression_manager.start_expression(index);
+        true
+    }
+
+    /// 表情をフェードアウトさせて元に戻す
+    pub fn clear_expression(&mut self) {
+        self.expression_manager.stop_expressions(&self.expressions);
+    }
+
+    /// 設定中の表情の名前
+    pub fn current_expression(&self) -> Option<&str> {
+        self.expression_manager
+            .current_expression()
+            .and_then(|index| self.expressions.get(index))
+            .map(|expression| expression.name.as_str())
+    }
+
+    /// 時間を進めて表情をパラメータに適用する
+    pub fn update_expression(&mut self, delta_time: f32) -> bool {
+        self.expression_manager
+            .update(&self.expressions, &mut self.resource, delta_time)
+    }
+
+    #[inline]
+    pub fn hit_test_mode(&self) -> HitTestMode {
+        self.hit_test_mode
+    }
+
+    /// hit_testとis_hitの判定方法 初期値は公式と同じ矩形
+    pub fn set_hit_test_mode(&mut self, mode: HitTestMode) {
+        self.hit_test_mode = mode;
+    }
+
+    /// x, yにあるHitAreaの名前
+    /// 重なっている場合は手前に描画されるdrawableのHitAreaを返す
+    /// x, yはモデルの座標 画面の座標はViewMatrixとModelMatrixで変換する
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<&str> {
+        let render_orders = self.resource.csm_get_drawable_render_orders();
+
+        self.hit_areas
+            .iter()
+            .filter_map(|hit_area| Some((hit_area, hit_area.drawable_index?)))
+            .filter(|(_, index)| {
+                hit_test::hit_drawable(&self.resource, *index, [x, y], self.hit_test_mode)
+            })
+            // 同じ順番なら先に書かれたものにする
+            .rev()
+            .max_by_key(|(_, index)| render_orders[*index])
+            .map(|(hit_area, _)| hit_area.name.as_str())
+    }
+
+    /// nameのHitAreaにx, yが含まれるか
+    pub fn is_hit(&self, name: &str, x: f32, y: f32) -> bool {
+        self.hit_areas
+            .iter()
+            .filter(|hit_area| hit_area.name == name)
+            .filter_map(|hit_area| hit_area.drawable_index)
+            .any(|index| hit_test::hit_drawable(&self.resource, index, [x, y], self.hit_test_mode))
+    }
+
+    /// x, yにある表示中のdrawableの番号を手前から順に返す
+    pub fn pick_drawables(&self, x: f32, y: f32) -> Vec<usize> {
+        hit_test::pick_drawables(&self.resource, [x, y], self.hit_test_mode)
+    }
+
+    /// 1フレーム分モデルを更新する
+    pub fn update(&mut self, delta_time: f32) {
+        self.update_motion(delta_time);
+        self.evaluate_eye_blink(delta_time);
+        self.update_expression(delta_time);
+        self.evaluate_drag(delta_time);
+        self.evaluate_breath(delta_time);
+        self.evaluate_physic(delta_time);
+        self.evaluate_lip_sync(delta_time);
+        self.evaluate_pose(delta_time);
         self.resource.update();
     }
 
-    fn replace_default_values(&self) {
-        for (value, default_value) in self
+    /// 保存しておいたパラメータを読み込む
+    pub fn load_parameters(&mut self) {
+        for (value, saved_value) in self
             .resource
             .csm_get_mut_parameter_values()
             .iter_mut()
-            .zip(self.resource.csm_get_parameter_default_values())
+            .zip(self.saved_parameters.iter())
         {
-            *value = *default_value;
+            *value = *saved_value;
         }
     }
+
+    /// 現在のパラメータを保存する
+    pub fn save_parameters(&mut self) {
+        self.saved_parameters.clear();
+        self.saved_parameters
+            .extend_from_slice(self.resource.csm_get_parameter_values());
+    }
+
+    fn replace_default_values(&mut self) {
+        let values = self.resource.values_mut();
+        values
+            .parameter_values
+            .copy_from_slice(values.parameter_default_values);
+    }
+}
+
+/// 何も再生していないときに再生するモーショングループ
+const IDLE_GROUP: &str = "Idle";
+
+/// まばたきのパラメータのグループ
+const EYE_BLINK_GROUP: &str = "EyeBlink";
+/// 口パクのパラメータのグループ
+const LIP_SYNC_GROUP: &str = "LipSync";
+
+/// model3.jsonのGroupsから名前が一致するグループのパラメータidを取得する
+fn group_ids(model_json: &model_json::ModelJson, name: &str) -> Vec<String> {
+    model_json
+        .Groups
+        .iter()
+        .flatten()
+        .filter(|group| group.Target == "Parameter" && group.Name == name)
+        .flat_map(|group| group.Ids.iter().cloned())
+        .collect()
+}
+
+/// jsonファイルを読み込んでデシリアライズする
+fn read_json<T, S>(source: &S, path: &Path) -> Result<T, Live2DFileError>
+where
+    T: serde::de::DeserializeOwned,
+    S: AssetSource + ?Sized,
+{
+    let bytes = source.read(path)?;
+
+    Ok(serde_json::from_slice(&bytes)?)
 }
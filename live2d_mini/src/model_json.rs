@@ -1,4 +1,6 @@
 #![allow(non_snake_case)]
+use std::collections::BTreeMap;
+
 use serde::*;
 
 #[derive(Debug, Deserialize)]
@@ -17,20 +19,22 @@ pub struct FileReferences {
     pub Pose: Option<String>,
     pub UserData: Option<String>,
     pub DisplayInfo: String,
-    pub Motions: Option<Motions>,
+    /// グループ名とモーションのリスト
+    pub Motions: Option<BTreeMap<String, Vec<Motion>>>,
+    pub Expressions: Option<Vec<Expression>>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct Motions {
-    pub Idle: Vec<Motion>,
-    pub TapBody: Vec<Motion>,
+pub struct Expression {
+    pub Name: String,
+    pub File: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Motion {
     pub File: String,
-    pub FadeInTime: f32,
-    pub FadeOutTime: f32,
+    pub FadeInTime: Option<f32>,
+    pub FadeOutTime: Option<f32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,17 +1,15 @@
-use std::alloc::alloc_zeroed;
-use std::alloc::Layout;
-use std::alloc::LayoutError;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io;
-use std::io::Read;
 use std::os::raw::c_char;
 use std::path::Path;
+use std::rc::Rc;
 
 use crate::address::*;
 use crate::constant_flag::Live2DConstantFlag;
 use crate::drawable::*;
 use crate::dynamic_flag::Live2DDynamicFlag;
+use crate::error::Live2DLoadError;
+use crate::jagged_slice::JaggedSlice;
+use crate::moc::Live2DMoc;
 use crate::parameter::*;
 use crate::part::*;
 
@@ -61,6 +59,27 @@ impl Live2DVector4 {
     }
 }
 
+/// 乗算色・スクリーン色
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Live2DColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Live2DColor {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Live2DColor { r, g, b, a }
+    }
+}
+
+impl From<&Live2DVector4> for Live2DColor {
+    fn from(vector: &Live2DVector4) -> Self {
+        Live2DColor::new(vector.r(), vector.g(), vector.b(), vector.a())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Live2DCanvasInfo {
     /// キャンバスサイズ
@@ -81,29 +100,93 @@ impl Live2DCanvasInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// idから取得したパラメータを指す
+/// 取得したモデルでのみ使える
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParameterHandle(usize);
+
+impl ParameterHandle {
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// idから取得したパーツを指す
+/// 取得したモデルでのみ使える
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PartHandle(usize);
+
+impl PartHandle {
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// パラメータ・パーツの不透明度・色の上書きを同時に書き換えるための借用
+/// それぞれ別の領域を指しているので同時に&mutで持てる
+#[derive(Debug)]
+pub struct Live2DModelValuesMut<'a> {
+    pub parameter_values: &'a mut [f32],
+    pub parameter_minimum_values: &'a [f32],
+    pub parameter_maximum_values: &'a [f32],
+    pub parameter_default_values: &'a [f32],
+    pub part_opacities: &'a mut [f32],
+    /// drawableごとの乗算色の上書き Noneならモデルの色を使う
+    pub multiply_color_overrides: &'a mut [Option<Live2DColor>],
+    /// drawableごとのスクリーン色の上書き Noneならモデルの色を使う
+    pub screen_color_overrides: &'a mut [Option<Live2DColor>],
+}
+
+// メモリを解放するのでClone不可
+#[derive(Debug, PartialEq)]
 pub struct Live2DModelResource {
+    // mocより先に解放する
     _model_address: Live2DAddress,
-    _moc_address: Live2DAddress,
+    moc: Rc<Live2DMoc>,
 
     model: *mut live2d_mini_sys::csmModel,
     not_exists_parameter_ids: HashMap<String, usize>,
+    /// パラメータのidとその番号
+    parameter_indices: HashMap<String, usize>,
+    /// パーツのidとその番号
+    part_indices: HashMap<String, usize>,
+    /// drawableのidとその番号
+    drawable_indices: HashMap<String, usize>,
+    multiply_color_overrides: Vec<Option<Live2DColor>>,
+    screen_color_overrides: Vec<Option<Live2DColor>>,
+    /// パーツごとの上書き 子のパーツとdrawableにも適用する
+    part_multiply_color_overrides: Vec<Option<Live2DColor>>,
+    part_screen_color_overrides: Vec<Option<Live2DColor>>,
 }
 
 impl Live2DModelResource {
     /// moc3ファイルを読み込んでLive2DModelを生成する
-    pub fn new<T>(moc_path: T) -> io::Result<Self>
+    pub fn new<T>(moc_path: T) -> Result<Self, Live2DLoadError>
     where
         T: AsRef<Path>,
     {
-        let model_resource = Self::read_blob_aligned(moc_path.as_ref())?;
+        Self::from_moc(Live2DMoc::new(moc_path)?)
+    }
 
-        Ok(model_resource)
+    /// メモリ上のmoc3からモデルを生成する
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Live2DLoadError> {
+        Self::from_moc(Live2DMoc::from_bytes(bytes)?)
     }
 
-    pub fn update(&self) {
-        self.csm_update_model();
+    /// 生成元のmoc
+    #[inline]
+    pub fn moc(&self) -> &Rc<Live2DMoc> {
+        &self.moc
+    }
+
+    /// パラメータをdrawableに反映する
+    /// dynamic flagは次のupdateまで残るので、描画側で変わったところを調べられる
+    /// 頂点座標などのスライスを書き換えるので&mutで借用する
+    pub fn update(&mut self) {
         self.csm_reset_drawable_dynamic_flags();
+        self.csm_update_model();
     }
 
     pub fn iter_drawables<'a>(&'a self) -> Live2DDrawableIter<'a> {
@@ -147,12 +230,22 @@ impl Live2DModelResource {
         }
     }
 
-    pub fn iter_mut_parameters<'a>(&'a self) -> Live2DParameterIterMut<'a> {
-        Live2DParameterIterMut {
-            pos: 0,
-            len: self.csm_get_parameter_count(),
+    pub fn iter_mut_parameters(&mut self) -> Live2DParameterIterMut<'_> {
+        // idは書き換えないので可変の借用とは別に取得する
+        let ids = unsafe {
+            std::slice::from_raw_parts(
+                live2d_mini_sys::csmGetParameterIds(self.model),
+                self.csm_get_parameter_count(),
+            )
+        };
+        let values = self.values_mut();
 
-            inner: self,
+        Live2DParameterIterMut {
+            ids: ids.iter(),
+            minimum_values: values.parameter_minimum_values.iter(),
+            maximum_values: values.parameter_maximum_values.iter(),
+            default_values: values.parameter_default_values.iter(),
+            values: values.parameter_values.iter_mut(),
         }
     }
 
@@ -165,77 +258,307 @@ impl Live2DModelResource {
         }
     }
 
-    pub fn iter_mut_parts<'a>(&'a self) -> Live2DPartIterMut<'a> {
+    pub fn iter_mut_parts(&mut self) -> Live2DPartIterMut<'_> {
+        // idと親の番号は書き換えないので可変の借用とは別に取得する
+        let (ids, parent_part_indices) = unsafe {
+            (
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetPartIds(self.model),
+                    self.csm_get_part_count(),
+                ),
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetPartParentPartIndices(self.model),
+                    self.csm_get_part_count(),
+                ),
+            )
+        };
+
         Live2DPartIterMut {
-            pos: 0,
-            len: self.csm_get_part_count(),
+            ids: ids.iter(),
+            opacities: self.values_mut().part_opacities.iter_mut(),
+            parent_part_indices: parent_part_indices.iter(),
+        }
+    }
 
-            inner: self,
+    /// パラメータ・パーツの不透明度・色の上書きを同時に書き換えられるように借用する
+    pub fn values_mut(&mut self) -> Live2DModelValuesMut<'_> {
+        let parameter_count = self.csm_get_parameter_count();
+        let part_count = self.csm_get_part_count();
+
+        // それぞれCubism Coreの別の配列なので重ならない
+        unsafe {
+            Live2DModelValuesMut {
+                parameter_values: std::slice::from_raw_parts_mut(
+                    live2d_mini_sys::csmGetParameterValues(self.model),
+                    parameter_count,
+                ),
+                parameter_minimum_values: std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetParameterMinimumValues(self.model),
+                    parameter_count,
+                ),
+                parameter_maximum_values: std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetParameterMaximumValues(self.model),
+                    parameter_count,
+                ),
+                parameter_default_values: std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetParameterDefaultValues(self.model),
+                    parameter_count,
+                ),
+                part_opacities: std::slice::from_raw_parts_mut(
+                    live2d_mini_sys::csmGetPartOpacities(self.model),
+                    part_count,
+                ),
+                multiply_color_overrides: &mut self.multiply_color_overrides,
+                screen_color_overrides: &mut self.screen_color_overrides,
+            }
         }
     }
 
-    unsafe fn allocate_aligned(size: usize, align: usize) -> Result<Live2DAddress, LayoutError> {
-        let layout = Layout::from_size_align(size, align)?;
-        Ok(Live2DAddress {
-            ptr: alloc_zeroed(layout),
-            layout,
-        })
+    /// drawableごとの乗算色の上書き
+    #[inline]
+    pub fn multiply_color_overrides(&self) -> &[Option<Live2DColor>] {
+        &self.multiply_color_overrides
     }
 
-    fn read_blob_aligned(path: &Path) -> io::Result<Self> {
-        let mut file = File::open(Path::new(path))?;
-        let file_size = file.metadata()?.len() as usize;
+    /// drawableごとのスクリーン色の上書き
+    #[inline]
+    pub fn screen_color_overrides(&self) -> &[Option<Live2DColor>] {
+        &self.screen_color_overrides
+    }
 
-        unsafe {
-            // このアドレスを破棄の対象にする
-            let moc_address =
-                Self::allocate_aligned(file_size, live2d_mini_sys::csmAlignofMoc as _)
-                    .expect("allocate error");
-            let moc_slice =
-                std::slice::from_raw_parts_mut(moc_address.ptr, moc_address.layout.size());
-            file.read(moc_slice)?;
-
-            // moc3ファイルの整合性を確認する
-            if live2d_mini_sys::csmHasMocConsistency(moc_address.ptr as _, file_size as _) == 0 {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "moc3 file is not consistent",
-                ));
-            }
+    /// パーツごとの乗算色の上書き
+    #[inline]
+    pub fn part_multiply_color_overrides(&self) -> &[Option<Live2DColor>] {
+        &self.part_multiply_color_overrides
+    }
+
+    /// パーツごとのスクリーン色の上書き
+    #[inline]
+    pub fn part_screen_color_overrides(&self) -> &[Option<Live2DColor>] {
+        &self.part_screen_color_overrides
+    }
+
+    /// drawableの乗算色を上書きする Noneでモデルの色に戻す
+    /// drawableがなければfalseを返す
+    pub fn set_multiply_color_override(
+        &mut self,
+        drawable_index: usize,
+        color: Option<Live2DColor>,
+    ) -> bool {
+        set_color_override(&mut self.multiply_color_overrides, drawable_index, color)
+    }
+
+    /// drawableのスクリーン色を上書きする Noneでモデルの色に戻す
+    /// drawableがなければfalseを返す
+    pub fn set_screen_color_override(
+        &mut self,
+        drawable_index: usize,
+        color: Option<Live2DColor>,
+    ) -> bool {
+        set_color_override(&mut self.screen_color_overrides, drawable_index, color)
+    }
+
+    /// パーツの乗算色を上書きする 子のパーツとdrawableにも適用する
+    /// 他のモデルのハンドルでパーツがなければfalseを返す
+    pub fn set_part_multiply_color_override(
+        &mut self,
+        handle: PartHandle,
+        color: Option<Live2DColor>,
+    ) -> bool {
+        set_color_override(&mut self.part_multiply_color_overrides, handle.0, color)
+    }
+
+    /// パーツのスクリーン色を上書きする 子のパーツとdrawableにも適用する
+    /// 他のモデルのハンドルでパーツがなければfalseを返す
+    pub fn set_part_screen_color_override(
+        &mut self,
+        handle: PartHandle,
+        color: Option<Live2DColor>,
+    ) -> bool {
+        set_color_override(&mut self.part_screen_color_overrides, handle.0, color)
+    }
 
-            let moc = live2d_mini_sys::csmReviveMocInPlace(moc_address.ptr as _, file_size as _);
+    /// 上書きを反映したdrawableの乗算色
+    /// drawable、近い親のパーツ、モデルの順に優先する
+    pub fn multiply_color(&self, drawable_index: usize) -> Live2DColor {
+        self.multiply_color_overrides[drawable_index]
+            .or_else(|| {
+                inherited_color(
+                    &self.part_multiply_color_overrides,
+                    self.csm_get_part_parent_part_indices(),
+                    self.csm_get_drawable_parent_part_indices()[drawable_index],
+                )
+            })
+            .unwrap_or_else(|| {
+                Live2DColor::from(&self.csm_get_drawable_multiply_colors()[drawable_index])
+            })
+    }
 
-            let model_size = live2d_mini_sys::csmGetSizeofModel(moc);
+    /// 上書きを反映したdrawableのスクリーン色
+    /// drawable、近い親のパーツ、モデルの順に優先する
+    pub fn screen_color(&self, drawable_index: usize) -> Live2DColor {
+        self.screen_color_overrides[drawable_index]
+            .or_else(|| {
+                inherited_color(
+                    &self.part_screen_color_overrides,
+                    self.csm_get_part_parent_part_indices(),
+                    self.csm_get_drawable_parent_part_indices()[drawable_index],
+                )
+            })
+            .unwrap_or_else(|| {
+                Live2DColor::from(&self.csm_get_drawable_screen_colors()[drawable_index])
+            })
+    }
+
+    /// 読み込んだmocからモデルを生成する
+    /// mocはこのモデルが破棄されるまで解放されない
+    pub fn from_moc(moc: Rc<Live2DMoc>) -> Result<Self, Live2DLoadError> {
+        unsafe {
+            let model_size = live2d_mini_sys::csmGetSizeofModel(moc.as_ptr());
             // このアドレスを破棄の対象にする
-            let model_address =
-                Self::allocate_aligned(model_size as _, live2d_mini_sys::csmAlignofModel as _)
-                    .expect("allocate error");
-            let model =
-                live2d_mini_sys::csmInitializeModelInPlace(moc, model_address.ptr as _, model_size);
+            let model_address = Live2DAddress::allocate_zeroed(
+                model_size as _,
+                live2d_mini_sys::csmAlignofModel as _,
+            )
+            .map_err(|source| Live2DLoadError::MocAllocation {
+                path: moc.path().to_path_buf(),
+                source,
+            })?;
+            let model = live2d_mini_sys::csmInitializeModelInPlace(
+                moc.as_ptr(),
+                model_address.ptr as _,
+                model_size,
+            );
+            if model.is_null() {
+                return Err(Live2DLoadError::ModelInitialization {
+                    path: moc.path().to_path_buf(),
+                });
+            }
 
-            Ok(Self {
+            let mut resource = Self {
                 _model_address: model_address,
-                _moc_address: moc_address,
+                moc,
 
                 model,
                 not_exists_parameter_ids: HashMap::new(),
-            })
+                parameter_indices: HashMap::new(),
+                part_indices: HashMap::new(),
+                drawable_indices: HashMap::new(),
+                multiply_color_overrides: vec![],
+                screen_color_overrides: vec![],
+                part_multiply_color_overrides: vec![],
+                part_screen_color_overrides: vec![],
+            };
+
+            // idの文字列変換と探索を毎回しなくて済むようにしておく
+            let parameter_indices = resource
+                .iter_parameters()
+                .enumerate()
+                .map(|(index, param)| (param.id().to_string(), index))
+                .collect();
+            let part_indices = resource
+                .iter_parts()
+                .enumerate()
+                .map(|(index, part)| (part.id().to_string(), index))
+                .collect();
+            let drawable_indices = resource
+                .iter_drawables()
+                .map(|drawable| (drawable.id().to_string(), drawable.index()))
+                .collect();
+            resource.parameter_indices = parameter_indices;
+            resource.part_indices = part_indices;
+            resource.drawable_indices = drawable_indices;
+
+            let drawable_count = resource.csm_get_drawable_count();
+            resource.multiply_color_overrides = vec![None; drawable_count];
+            resource.screen_color_overrides = vec![None; drawable_count];
+            let part_count = resource.csm_get_part_count();
+            resource.part_multiply_color_overrides = vec![None; part_count];
+            resource.part_screen_color_overrides = vec![None; part_count];
+
+            Ok(resource)
         }
     }
 
+    /// idのパラメータの番号
+    #[inline]
+    pub fn parameter_index(&self, id: &str) -> Option<usize> {
+        self.parameter_indices.get(id).copied()
+    }
+
+    /// idのパーツの番号
+    #[inline]
+    pub fn part_index(&self, id: &str) -> Option<usize> {
+        self.part_indices.get(id).copied()
+    }
+
+    /// idのdrawableの番号
+    #[inline]
+    pub fn drawable_index(&self, id: &str) -> Option<usize> {
+        self.drawable_indices.get(id).copied()
+    }
+
+    /// idのパラメータを取得する
+    #[inline]
+    pub fn parameter_handle(&self, id: &str) -> Option<ParameterHandle> {
+        self.parameter_index(id).map(ParameterHandle)
+    }
+
+    /// idのパーツを取得する
+    #[inline]
+    pub fn part_handle(&self, id: &str) -> Option<PartHandle> {
+        self.part_index(id).map(PartHandle)
+    }
+
+    #[inline]
+    pub fn parameter_value(&self, handle: ParameterHandle) -> f32 {
+        self.csm_get_parameter_values()[handle.0]
+    }
+
+    #[inline]
+    pub fn set_parameter_value(&mut self, handle: ParameterHandle, value: f32) {
+        self.csm_get_mut_parameter_values()[handle.0] = value;
+    }
+
+    /// 現在の値にvalue * weightを加算する
+    #[inline]
+    pub fn add_parameter_value(&mut self, handle: ParameterHandle, value: f32, weight: f32) {
+        self.csm_get_mut_parameter_values()[handle.0] += value * weight;
+    }
+
+    #[inline]
+    pub fn parameter_minimum_value(&self, handle: ParameterHandle) -> f32 {
+        self.csm_get_parameter_minimum_values()[handle.0]
+    }
+
+    #[inline]
+    pub fn parameter_maximum_value(&self, handle: ParameterHandle) -> f32 {
+        self.csm_get_parameter_maximum_values()[handle.0]
+    }
+
+    #[inline]
+    pub fn part_opacity(&self, handle: PartHandle) -> f32 {
+        self.csm_get_part_opacities()[handle.0]
+    }
+
+    #[inline]
+    pub fn set_part_opacity(&mut self, handle: PartHandle, opacity: f32) {
+        self.csm_get_mut_part_opacities()[handle.0] = opacity;
+    }
+
     // parameter idからindexを取得する
     // idがないものが渡される可能性があるのでそれを考慮する
     pub fn get_parameter_index(&mut self, id: &str) -> usize {
-        // dbg!(self.csm_get_parameter_count());
         // jsonデータにあるパラメータか
-        if let Some(index) = self.iter_parameters().position(|param| param.id() == id) {
+        if let Some(index) = self.parameter_index(id) {
             index
         } else {
             // 存在していないパラメータリストに存在しているか
             if let Some(index) = self.not_exists_parameter_ids.get(id) {
                 *index
             } else {
-                let index = self.csm_get_drawable_count() + self.not_exists_parameter_ids.len();
+                // 実在するパラメータの番号と重ならないようにする
+                let index = self.csm_get_parameter_count() + self.not_exists_parameter_ids.len();
                 self.not_exists_parameter_ids.insert(id.to_string(), index);
 
                 index
@@ -265,7 +588,7 @@ impl Live2DModelResource {
 
     /// 更新を適用する
     #[inline]
-    pub fn csm_update_model(&self) {
+    pub fn csm_update_model(&mut self) {
         unsafe { live2d_mini_sys::csmUpdateModel(self.model) }
     }
 
@@ -280,7 +603,7 @@ impl Live2DModelResource {
     }
 
     #[inline]
-    pub fn csm_get_parameter_ids(&self) -> &[*const c_char] {
+    pub(crate) fn csm_get_parameter_ids(&self) -> &[*const c_char] {
         unsafe {
             std::slice::from_raw_parts(
                 live2d_mini_sys::csmGetParameterIds(self.model),
@@ -324,14 +647,14 @@ impl Live2DModelResource {
         unsafe {
             std::slice::from_raw_parts(
                 live2d_mini_sys::csmGetParameterDefaultValues(self.model),
-                self.csm_get_drawable_count(),
+                self.csm_get_parameter_count(),
             )
         }
     }
 
     // ここに書き込むとmodelを操作できる
     #[inline]
-    pub fn csm_get_mut_parameter_values<'a>(&self) -> &mut [f32] {
+    pub fn csm_get_mut_parameter_values(&mut self) -> &mut [f32] {
         unsafe {
             std::slice::from_raw_parts_mut(
                 live2d_mini_sys::csmGetParameterValues(self.model),
@@ -355,17 +678,21 @@ impl Live2DModelResource {
         unsafe {
             std::slice::from_raw_parts(
                 live2d_mini_sys::csmGetParameterKeyCounts(self.model),
-                self.csm_get_part_count(),
+                self.csm_get_parameter_count(),
             )
         }
     }
 
+    /// パラメータごとのキーの値
     #[inline]
-    pub fn csm_get_parameter_key_values<'a>(&self) -> &[*const f32] {
+    pub fn csm_get_parameter_key_values(&self) -> JaggedSlice<'_, f32> {
         unsafe {
-            std::slice::from_raw_parts(
-                live2d_mini_sys::csmGetParameterKeyValues(self.model),
-                self.csm_get_part_count(),
+            JaggedSlice::new(
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetParameterKeyValues(self.model),
+                    self.csm_get_parameter_count(),
+                ),
+                self.csm_get_parameter_key_counts(),
             )
         }
     }
@@ -381,7 +708,7 @@ impl Live2DModelResource {
     }
 
     #[inline]
-    pub fn csm_get_part_ids(&self) -> &[*const c_char] {
+    pub(crate) fn csm_get_part_ids(&self) -> &[*const c_char] {
         unsafe {
             std::slice::from_raw_parts(
                 live2d_mini_sys::csmGetPartIds(self.model),
@@ -391,7 +718,18 @@ impl Live2DModelResource {
     }
 
     #[inline]
-    pub fn csm_get_part_opacities(&self) -> &mut [f32] {
+    pub fn csm_get_part_opacities(&self) -> &[f32] {
+        unsafe {
+            std::slice::from_raw_parts(
+                live2d_mini_sys::csmGetPartOpacities(self.model),
+                self.csm_get_part_count(),
+            )
+        }
+    }
+
+    // ここに書き込むとパーツの不透明度を操作できる
+    #[inline]
+    pub fn csm_get_mut_part_opacities(&mut self) -> &mut [f32] {
         unsafe {
             std::slice::from_raw_parts_mut(
                 live2d_mini_sys::csmGetPartOpacities(self.model),
@@ -421,7 +759,7 @@ impl Live2DModelResource {
     }
 
     #[inline]
-    pub fn csm_get_drawable_ids<'a>(&self) -> &[*const c_char] {
+    pub(crate) fn csm_get_drawable_ids<'a>(&self) -> &[*const c_char] {
         unsafe {
             std::slice::from_raw_parts(
                 live2d_mini_sys::csmGetDrawableIds(self.model),
@@ -501,17 +839,28 @@ impl Live2DModelResource {
         }
     }
 
-    //配列の配列をなんとかする
+    /// drawableごとのマスクに使うdrawableの番号
     #[inline]
-    pub fn csm_get_drawable_masks(&self) -> &[*const i32] {
+    pub fn csm_get_drawable_masks(&self) -> JaggedSlice<'_, i32> {
         unsafe {
-            std::slice::from_raw_parts(
-                live2d_mini_sys::csmGetDrawableMasks(self.model),
-                self.csm_get_drawable_count(),
+            JaggedSlice::new(
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetDrawableMasks(self.model),
+                    self.csm_get_drawable_count(),
+                ),
+                self.csm_get_drawable_mask_counts(),
             )
         }
     }
 
+    /// index番目のdrawableのマスクに使うdrawableの番号
+    #[inline]
+    pub fn drawable_masks(&self, index: usize) -> &[i32] {
+        self.csm_get_drawable_masks()
+            .get(index)
+            .expect("drawable index out of range")
+    }
+
     #[inline]
     pub fn csm_get_drawable_vertex_counts(&self) -> &[i32] {
         unsafe {
@@ -522,28 +871,50 @@ impl Live2DModelResource {
         }
     }
 
-    //配列の配列をなんとかする
+    /// drawableごとの頂点の座標
     #[inline]
-    pub fn csm_get_drawable_vertex_positions(&self) -> &[*const Live2DVector2] {
+    pub fn csm_get_drawable_vertex_positions(&self) -> JaggedSlice<'_, Live2DVector2> {
         unsafe {
-            std::slice::from_raw_parts(
-                live2d_mini_sys::csmGetDrawableVertexPositions(self.model) as _,
-                self.csm_get_drawable_count(),
+            JaggedSlice::new(
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetDrawableVertexPositions(self.model) as _,
+                    self.csm_get_drawable_count(),
+                ),
+                self.csm_get_drawable_vertex_counts(),
             )
         }
     }
 
-    //配列の配列をなんとかする
+    /// index番目のdrawableの頂点の座標
+    #[inline]
+    pub fn drawable_vertex_positions(&self, index: usize) -> &[Live2DVector2] {
+        self.csm_get_drawable_vertex_positions()
+            .get(index)
+            .expect("drawable index out of range")
+    }
+
+    /// drawableごとの頂点のuv
     #[inline]
-    pub fn csm_get_drawable_vertex_uvs(&self) -> &[*const Live2DVector2] {
+    pub fn csm_get_drawable_vertex_uvs(&self) -> JaggedSlice<'_, Live2DVector2> {
         unsafe {
-            std::slice::from_raw_parts(
-                live2d_mini_sys::csmGetDrawableVertexUvs(self.model) as _,
-                self.csm_get_drawable_count(),
+            JaggedSlice::new(
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetDrawableVertexUvs(self.model) as _,
+                    self.csm_get_drawable_count(),
+                ),
+                self.csm_get_drawable_vertex_counts(),
             )
         }
     }
 
+    /// index番目のdrawableの頂点のuv
+    #[inline]
+    pub fn drawable_vertex_uvs(&self, index: usize) -> &[Live2DVector2] {
+        self.csm_get_drawable_vertex_uvs()
+            .get(index)
+            .expect("drawable index out of range")
+    }
+
     #[inline]
     pub fn csm_get_drawable_index_counts(&self) -> &[i32] {
         unsafe {
@@ -554,24 +925,35 @@ impl Live2DModelResource {
         }
     }
 
-    //配列の配列をなんとかする
+    /// drawableごとの三角形の頂点の番号
     #[inline]
-    pub fn csm_get_drawable_indices(&self) -> &[*const u16] {
+    pub fn csm_get_drawable_indices(&self) -> JaggedSlice<'_, u16> {
         unsafe {
-            std::slice::from_raw_parts(
-                live2d_mini_sys::csmGetDrawableIndices(self.model),
-                self.csm_get_drawable_count(),
+            JaggedSlice::new(
+                std::slice::from_raw_parts(
+                    live2d_mini_sys::csmGetDrawableIndices(self.model),
+                    self.csm_get_drawable_count(),
+                ),
+                self.csm_get_drawable_index_counts(),
             )
         }
     }
 
+    /// index番目のdrawableの三角形の頂点の番号
+    #[inline]
+    pub fn drawable_indices(&self, index: usize) -> &[u16] {
+        self.csm_get_drawable_indices()
+            .get(index)
+            .expect("drawable index out of range")
+    }
+
     #[inline]
-    pub fn csm_reset_drawable_dynamic_flags(&self) {
+    pub fn csm_reset_drawable_dynamic_flags(&mut self) {
         unsafe { live2d_mini_sys::csmResetDrawableDynamicFlags(self.model) };
     }
 
     #[inline]
-    pub fn csm_get_drawable_multiply_colors(&self) -> &[*const Live2DVector4] {
+    pub fn csm_get_drawable_multiply_colors(&self) -> &[Live2DVector4] {
         unsafe {
             std::slice::from_raw_parts(
                 live2d_mini_sys::csmGetDrawableMultiplyColors(self.model) as _,
@@ -600,3 +982,79 @@ impl Live2DModelResource {
         }
     }
 }
+
+/// indexが範囲内なら上書きしてtrueを返す
+fn set_color_override(
+    overrides: &mut [Option<Live2DColor>],
+    index: usize,
+    color: Option<Live2DColor>,
+) -> bool {
+    match overrides.get_mut(index) {
+        Some(value) => {
+            *value = color;
+            true
+        }
+        None => false,
+    }
+}
+
+/// partから親をたどって最初に見つかった上書き
+/// 親がない場合は-1
+fn inherited_color(
+    part_overrides: &[Option<Live2DColor>],
+    parent_part_indices: &[i32],
+    part_index: i32,
+) -> Option<Live2DColor> {
+    let mut part_index = part_index;
+    // 親子関係が壊れていても止まるようにパーツの数までしかたどらない
+    for _ in 0..part_overrides.len() {
+        let index = usize::try_from(part_index).ok()?;
+        if let Some(color) = part_overrides.get(index)? {
+            return Some(*color);
+        }
+        part_index = *parent_part_indices.get(index)?;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inherited_color_test() {
+        let red = Live2DColor::new(1.0, 0.0, 0.0, 1.0);
+        let blue = Live2DColor::new(0.0, 0.0, 1.0, 1.0);
+        // 0 <- 1 <- 2, 3は親なし
+        let parents = [-1, 0, 1, -1];
+        let mut overrides = vec![None; 4];
+
+        assert_eq!(inherited_color(&overrides, &parents, 2), None);
+        assert_eq!(inherited_color(&overrides, &parents, -1), None);
+
+        overrides[0] = Some(red);
+        assert_eq!(inherited_color(&overrides, &parents, 2), Some(red));
+        assert_eq!(inherited_color(&overrides, &parents, 3), None);
+
+        // 近い親を優先する
+        overrides[1] = Some(blue);
+        assert_eq!(inherited_color(&overrides, &parents, 2), Some(blue));
+        assert_eq!(inherited_color(&overrides, &parents, 0), Some(red));
+
+        // 循環していても止まる
+        assert_eq!(inherited_color(&[None, None], &[1, 0], 0), None);
+    }
+
+    #[test]
+    fn set_color_override_test() {
+        let color = Live2DColor::new(1.0, 0.5, 0.0, 1.0);
+        let mut overrides = vec![None; 2];
+
+        assert!(set_color_override(&mut overrides, 1, Some(color)));
+        assert_eq!(overrides, vec![None, Some(color)]);
+        assert!(!set_color_override(&mut overrides, 2, Some(color)));
+        assert!(set_color_override(&mut overrides, 1, None));
+        assert_eq!(overrides, vec![None, None]);
+    }
+}
@@ -20,12 +20,16 @@ pub struct Meta {
     pub TotalPointCount: i32,
     pub UserDataCount: i32,
     pub TotalUserDataSize: i32,
+    pub FadeInTime: Option<f32>,
+    pub FadeOutTime: Option<f32>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Curve {
     pub Target: String,
     pub Id: String,
+    pub FadeInTime: Option<f32>,
+    pub FadeOutTime: Option<f32>,
     pub Segments: Vec<f32>,
 }
 
@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+
+use crate::animation::Animation;
+use crate::model_resource::Live2DModelResource;
+use crate::motion_queue::{MotionHandle, MotionQueue};
+
+/// モーションの優先度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum MotionPriority {
+    #[default]
+    None,
+    Idle,
+    Normal,
+    Force,
+}
+
+/// 再生を要求されたモーション
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MotionRequest {
+    pub group: String,
+    pub index: usize,
+    pub priority: MotionPriority,
+}
+
+/// 再生が終わったモーション
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinishedMotion {
+    pub handle: MotionHandle,
+    pub group: Option<String>,
+    pub index: usize,
+    pub priority: MotionPriority,
+}
+
+/// 再生中のモーションの情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlayingMotion {
+    handle: MotionHandle,
+    group: Option<String>,
+    index: usize,
+    priority: MotionPriority,
+}
+
+/// 優先度つきでモーションの再生を管理する
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Motion/CubismMotionManager.cpp
+#[derive(Debug, Clone)]
+pub struct MotionManager {
+    queue: MotionQueue,
+    /// 再生中のモーションの優先度
+    current_priority: MotionPriority,
+    /// 再生予定のモーションの優先度
+    reserve_priority: MotionPriority,
+    /// 再生中のモーション
+    playing: Vec<PlayingMotion>,
+    /// 再生中のモーションが終わった後に再生するモーション
+    pending: VecDeque<MotionRequest>,
+    /// 直前のupdateで再生が終わったモーション
+    finished: Vec<FinishedMotion>,
+    /// 何も再生していないときに再生するグループ
+    idle_group: Option<String>,
+    rng: fastrand::Rng,
+}
+
+impl Default for MotionManager {
+    fn default() -> Self {
+        MotionManager {
+            queue: MotionQueue::new(),
+            current_priority: MotionPriority::None,
+            reserve_priority: MotionPriority::None,
+            playing: vec![],
+            pending: VecDeque::new(),
+            finished: vec![],
+            idle_group: None,
+            rng: fastrand::Rng::new(),
+        }
+    }
+}
+
+impl MotionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn current_priority(&self) -> MotionPriority {
+        self.current_priority
+    }
+
+    #[inline]
+    pub fn reserve_priority(&self) -> MotionPriority {
+        self.reserve_priority
+    }
+
+    #[inline]
+    pub fn set_reserve_priority(&mut self, priority: MotionPriority) {
+        self.reserve_priority = priority;
+    }
+
+    /// 何も再生していないときに再生するグループを設定する
+    pub fn set_idle_group(&mut self, group: Option<String>) {
+        self.idle_group = group;
+    }
+
+    #[inline]
+    pub fn idle_group(&self) -> Option<&str> {
+        self.idle_group.as_deref()
+    }
+
+    /// 乱数のシードを設定する
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    /// 優先度を指定してモーションを予約する
+    /// 再生中・予約済みのモーションの優先度以下なら予約できない
+    pub fn reserve_motion(&mut self, priority: MotionPriority) -> bool {
+        if priority <= self.reserve_priority || priority <= self.current_priority {
+            return false;
+        }
+
+        self.reserve_priority = priority;
+        true
+    }
+
+    /// 優先度を指定してanimationの番号のモーションを再生する
+    /// Force以外は予約できなければ再生しない
+    pub fn start_motion_priority(
+        &mut self,
+        animation_index: usize,
+        group: Option<&str>,
+        index: usize,
+        priority: MotionPriority,
+        animations: &mut [Animation],
+    ) -> Option<MotionHandle> {
+        if priority == MotionPriority::Force {
+            self.reserve_priority = priority;
+        } else if !self.reserve_motion(priority) {
+            return None;
+        }
+
+        Some(self.start(animation_index, group, index, priority, animations))
+    }
+
+    /// 再生中のモーションが終わったら再生するモーションを追加する
+    pub fn queue_motion(&mut self, request: MotionRequest) {
+        self.pending.push_back(request);
+    }
+
+    /// 再生待ちのモーションを全て取り消す
+    pub fn clear_queued_motions(&mut self) {
+        self.pending.clear();
+    }
+
+    /// 次に再生するモーションを取り出す
+    /// 再生待ちのモーションがなければidleグループから選ぶ
+    pub(crate) fn next_request(
+        &mut self,
+        group_len: impl Fn(&str) -> usize,
+    ) -> Option<MotionRequest> {
+        if let Some(request) = self.pending.pop_front() {
+            return Some(request);
+        }
+
+        let group = self.idle_group.as_ref()?;
+        let len = group_len(group);
+        if len == 0 {
+            return None;
+        }
+
+        Some(MotionRequest {
+            group: group.clone(),
+            index: self.rng.usize(..len),
+            priority: MotionPriority::Idle,
+        })
+    }
+
+    /// 0..lenの乱数
+    pub(crate) fn random_index(&mut self, len: usize) -> usize {
+        self.rng.usize(..len)
+    }
+
+    fn start(
+        &mut self,
+        animation_index: usize,
+        group: Option<&str>,
+        index: usize,
+        priority: MotionPriority,
+        animations: &mut [Animation],
+    ) -> MotionHandle {
+        if priority == self.reserve_priority {
+            self.reserve_priority = MotionPriority::None;
+        }
+        self.current_priority = priority;
+
+        let handle = self.queue.start_motion(animation_index, animations);
+        self.playing.push(PlayingMotion {
+            handle,
+            group: group.map(|group| group.to_string()),
+            index,
+            priority,
+        });
+
+        handle
+    }
+
+    /// 再生中のモーションを全て止める
+    pub fn stop_all_motions(&mut self) {
+        self.finished.clear();
+        self.queue.stop_all_motions();
+        self.collect_finished();
+        self.current_priority = MotionPriority::None;
+    }
+
+    /// 全てのモーションの再生が終わったか
+    pub fn is_finished(&self) -> bool {
+        self.queue.is_finished()
+    }
+
+    /// handleのモーションが再生中か
+    pub fn is_playing(&self, handle: MotionHandle) -> bool {
+        self.queue.is_playing(handle)
+    }
+
+    /// 直前のupdateで再生が終わったモーション
+    pub fn finished_motions(&self) -> &[FinishedMotion] {
+        &self.finished
+    }
+
+    /// 時間を進めてモーションをパラメータに適用する
+    /// 一つでもモーションを適用した場合はtrueを返す
+    pub fn update(
+        &mut self,
+        animations: &mut [Animation],
+        model: &mut Live2DModelResource,
+        delta_time: f32,
+    ) -> bool {
+        self.finished.clear();
+        let updated = self.queue.update(animations, model, delta_time);
+        self.collect_finished();
+
+        if self.queue.is_finished() {
+            self.current_priority = MotionPriority::None;
+        }
+
+        updated
+    }
+
+    fn collect_finished(&mut self) {
+        for handle in self.queue.take_finished() {
+            if let Some(position) = self
+                .playing
+                .iter()
+                .position(|playing| playing.handle == handle)
+            {
+                let playing = self.playing.remove(position);
+                self.finished.push(FinishedMotion {
+                    handle,
+                    group: playing.group,
+                    index: playing.index,
+                    priority: playing.priority,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority_test() {
+        let mut manager = MotionManager::new();
+        let mut animations = vec![Animation::default(), Animation::default()];
+
+        assert!(manager
+            .start_motion_priority(0, None, 0, MotionPriority::Normal, &mut animations)
+            .is_some());
+        assert_eq!(manager.current_priority(), MotionPriority::Normal);
+        assert_eq!(manager.reserve_priority(), MotionPriority::None);
+
+        // 再生中のモーション以下の優先度は再生できない
+        assert!(manager
+            .start_motion_priority(1, None, 1, MotionPriority::Idle, &mut animations)
+            .is_none());
+        assert!(manager
+            .start_motion_priority(1, None, 1, MotionPriority::Normal, &mut animations)
+            .is_none());
+
+        assert!(manager
+            .start_motion_priority(1, None, 1, MotionPriority::Force, &mut animations)
+            .is_some());
+        assert_eq!(manager.current_priority(), MotionPriority::Force);
+
+        manager.stop_all_motions();
+        assert_eq!(manager.current_priority(), MotionPriority::None);
+        assert_eq!(manager.finished_motions().len(), 2);
+    }
+
+    #[test]
+    fn next_request_test() {
+        let mut manager = MotionManager::new();
+        assert_eq!(manager.next_request(|_| 3), None);
+
+        manager.set_idle_group(Some("Idle".to_string()));
+        manager.queue_motion(MotionRequest {
+            group: "TapBody".to_string(),
+            index: 0,
+            priority: MotionPriority::Normal,
+        });
+
+        assert_eq!(
+            manager.next_request(|_| 3).map(|request| request.group),
+            Some("TapBody".to_string())
+        );
+
+        let request = manager.next_request(|_| 3).unwrap();
+        assert_eq!(request.group, "Idle");
+        assert_eq!(request.priority, MotionPriority::Idle);
+        assert!(request.index < 3);
+
+        assert_eq!(manager.next_request(|_| 0), None);
+    }
+}
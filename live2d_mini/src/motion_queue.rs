@@ -0,0 +1,189 @@
+use crate::animation::{Animation, AnimationFade};
+use crate::model_resource::Live2DModelResource;
+
+/// 再生を開始したモーションを識別する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MotionHandle(u64);
+
+/// 再生中のモーション一つ分の状態
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionQueueEntry {
+    pub handle: MotionHandle,
+    /// 再生するanimationの番号
+    pub animation_index: usize,
+    /// 再生を開始した時刻
+    start_time: Option<f32>,
+    /// フェードインを開始した時刻
+    fade_in_start_time: f32,
+    /// 再生を終了する時刻 Noneなら終わらない
+    end_time: Option<f32>,
+    /// 再生が終了したか
+    finished: bool,
+}
+
+impl MotionQueueEntry {
+    fn new(handle: MotionHandle, animation_index: usize) -> Self {
+        MotionQueueEntry {
+            handle,
+            animation_index,
+            start_time: None,
+            fade_in_start_time: 0.0,
+            end_time: None,
+            finished: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// フェードアウトを開始する
+    fn start_fade_out(&mut self, fade_out_time: f32, user_time: f32) {
+        let new_end_time = user_time + fade_out_time;
+        match self.end_time {
+            Some(end_time) if end_time <= new_end_time => {}
+            _ => self.end_time = Some(new_end_time),
+        }
+    }
+}
+
+/// 再生中のモーションを管理し、切り替え時にクロスフェードさせる
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MotionQueue {
+    entries: Vec<MotionQueueEntry>,
+    /// 経過時間の合計
+    user_time: f32,
+    /// 次に発行するhandle
+    next_handle: u64,
+    /// 再生が終わったモーション
+    finished: Vec<MotionHandle>,
+}
+
+impl MotionQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// モーションを開始する
+    /// 再生中のモーションはそれぞれのフェードアウト時間でフェードアウトさせる
+    pub fn start_motion(
+        &mut self,
+        animation_index: usize,
+        animations: &mut [Animation],
+    ) -> MotionHandle {
+        for entry in self.entries.iter_mut() {
+            if let Some(animation) = animations.get(entry.animation_index) {
+                entry.start_fade_out(animation.fade_out_time, self.user_time);
+            }
+        }
+
+        if let Some(animation) = animations.get_mut(animation_index) {
+            animation.reset_evaluate_indeies();
+        }
+        let handle = MotionHandle(self.next_handle);
+        self.next_handle += 1;
+        self.entries
+            .push(MotionQueueEntry::new(handle, animation_index));
+
+        handle
+    }
+
+    /// 再生中のモーションを全て止める
+    pub fn stop_all_motions(&mut self) {
+        self.finished
+            .extend(self.entries.drain(..).map(|entry| entry.handle));
+    }
+
+    /// handleのモーションが再生中か
+    pub fn is_playing(&self, handle: MotionHandle) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.handle == handle && !entry.is_finished())
+    }
+
+    /// 前回呼び出してから再生が終わったモーションを取り出す
+    pub fn take_finished(&mut self) -> Vec<MotionHandle> {
+        std::mem::take(&mut self.finished)
+    }
+
+    /// 全てのモーションの再生が終わったか
+    pub fn is_finished(&self) -> bool {
+        self.entries.iter().all(|entry| entry.is_finished())
+    }
+
+    /// 再生中のモーション
+    pub fn entries(&self) -> &[MotionQueueEntry] {
+        &self.entries
+    }
+
+    /// 時間を進めてモーションをパラメータに適用する
+    /// 一つでもモーションを適用した場合はtrueを返す
+    pub fn update(
+        &mut self,
+        animations: &mut [Animation],
+        model: &mut Live2DModelResource,
+        delta_time: f32,
+    ) -> bool {
+        self.user_time += delta_time;
+        let user_time = self.user_time;
+
+        let mut updated = false;
+        for entry in self.entries.iter_mut() {
+            let animation = match animations.get_mut(entry.animation_index) {
+                Some(animation) => animation,
+                None => {
+                    entry.finished = true;
+                    continue;
+                }
+            };
+
+            let start_time = match entry.start_time {
+                Some(start_time) => start_time,
+                None => {
+                    entry.start_time = Some(user_time);
+                    entry.fade_in_start_time = user_time;
+                    if !animation.is_loop && animation.duration > 0.0 {
+                        let end_time = user_time + animation.duration;
+                        if entry.end_time.map(|t| end_time < t).unwrap_or(true) {
+                            entry.end_time = Some(end_time);
+                        }
+                    }
+                    user_time
+                }
+            };
+
+            let mut time = user_time - start_time;
+            if animation.is_loop && animation.duration > 0.0 {
+                time %= animation.duration;
+            } else if time > animation.duration {
+                time = animation.duration;
+            }
+
+            let fade = AnimationFade {
+                fade_in_elapsed: user_time - entry.fade_in_start_time,
+                fade_out_remaining: entry.end_time.map(|end_time| end_time - user_time),
+            };
+            animation.evaluate_animation_with_fade(model, time, fade);
+            updated = true;
+
+            if let Some(end_time) = entry.end_time {
+                if end_time < user_time {
+                    entry.finished = true;
+                }
+            }
+        }
+
+        let finished = &mut self.finished;
+        self.entries.retain(|entry| {
+            if entry.is_finished() {
+                finished.push(entry.handle);
+                false
+            } else {
+                true
+            }
+        });
+
+        updated
+    }
+}
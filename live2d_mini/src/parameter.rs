@@ -18,7 +18,7 @@ impl<'a> Live2DParameter<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Live2DParameterIter<'a> {
     pub(crate) pos: usize,
     pub(crate) len: usize,
@@ -79,47 +79,25 @@ impl<'a> Live2DParameterMut<'a> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug)]
 pub struct Live2DParameterIterMut<'a> {
-    pub(crate) pos: usize,
-    pub(crate) len: usize,
-
-    pub(crate) inner: &'a Live2DModelResource,
+    pub(crate) ids: std::slice::Iter<'a, *const c_char>,
+    pub(crate) minimum_values: std::slice::Iter<'a, f32>,
+    pub(crate) maximum_values: std::slice::Iter<'a, f32>,
+    pub(crate) default_values: std::slice::Iter<'a, f32>,
+    pub(crate) values: std::slice::IterMut<'a, f32>,
 }
 
 impl<'a> Iterator for Live2DParameterIterMut<'a> {
     type Item = Live2DParameterMut<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.len {
-            return None;
-        } else {
-            self.pos += 1;
-
-            unsafe {
-                Some(Live2DParameterMut {
-                    id: self
-                        .inner
-                        .csm_get_parameter_ids()
-                        .get_unchecked(self.pos - 1),
-                    minimum_value: self
-                        .inner
-                        .csm_get_parameter_minimum_values()
-                        .get_unchecked(self.pos - 1),
-                    maximum_value: self
-                        .inner
-                        .csm_get_parameter_maximum_values()
-                        .get_unchecked(self.pos - 1),
-                    default_value: self
-                        .inner
-                        .csm_get_parameter_default_values()
-                        .get_unchecked(self.pos - 1),
-                    value: self
-                        .inner
-                        .csm_get_mut_parameter_values()
-                        .get_unchecked_mut(self.pos - 1),
-                })
-            }
-        }
+        Some(Live2DParameterMut {
+            id: self.ids.next()?,
+            minimum_value: self.minimum_values.next()?,
+            maximum_value: self.maximum_values.next()?,
+            default_value: self.default_values.next()?,
+            value: self.values.next()?,
+        })
     }
 }
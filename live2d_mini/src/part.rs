@@ -26,7 +26,7 @@ impl<'a> Live2DPart<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Live2DPartIter<'a> {
     pub(crate) pos: usize,
     pub(crate) len: usize,
@@ -73,36 +73,21 @@ impl<'a> Live2DPartMut<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug)]
 pub struct Live2DPartIterMut<'a> {
-    pub(crate) pos: usize,
-    pub(crate) len: usize,
-
-    pub(crate) inner: &'a Live2DModelResource,
+    pub(crate) ids: std::slice::Iter<'a, *const c_char>,
+    pub(crate) opacities: std::slice::IterMut<'a, f32>,
+    pub(crate) parent_part_indices: std::slice::Iter<'a, i32>,
 }
 
 impl<'a> Iterator for Live2DPartIterMut<'a> {
     type Item = Live2DPartMut<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.len {
-            return None;
-        } else {
-            self.pos += 1;
-
-            unsafe {
-                Some(Live2DPartMut {
-                    id: self.inner.csm_get_part_ids().get_unchecked(self.pos - 1),
-                    opacitiy: self
-                        .inner
-                        .csm_get_part_opacities()
-                        .get_unchecked_mut(self.pos - 1),
-                    parent_part_index: self
-                        .inner
-                        .csm_get_part_parent_part_indices()
-                        .get_unchecked(self.pos - 1),
-                })
-            }
-        }
+        Some(Live2DPartMut {
+            id: self.ids.next()?,
+            opacitiy: self.opacities.next()?,
+            parent_part_index: self.parent_part_indices.next()?,
+        })
     }
 }
@@ -805,7 +805,7 @@ impl<'a> Physics {
     }
 
     // L837
-    fn interpolate(&mut self, model: &Live2DModelResource, weight: f32) {
+    fn interpolate(&mut self, model: &mut Live2DModelResource, weight: f32) {
         for setting_index in 0..self.physics_rig.sub_rig_count {
             let current_setting = &self.physics_rig.settings[setting_index];
             // let current_output = &self.physics_rig.outputs[current_setting.base_output_index];
@@ -820,10 +820,11 @@ impl<'a> Physics {
                 .enumerate()
             {
                 if let Some(index) = output.destination_parameter_index {
+                    let values = model.values_mut();
                     update_output_parameter_value(
-                        &mut model.csm_get_mut_parameter_values()[index],
-                        model.csm_get_parameter_minimum_values()[index],
-                        model.csm_get_parameter_maximum_values()[index],
+                        &mut values.parameter_values[index],
+                        values.parameter_minimum_values[index],
+                        values.parameter_maximum_values[index],
                         self.previous_rig_outputs[setting_index][i] * (1.0 - weight)
                             + self.current_rig_outputs[setting_index][i] * weight,
                         output,
@@ -1035,7 +1036,7 @@ impl<'a> Physics {
         }
         let alpha = self.current_remain_time / physics_delta_time;
 
-        self.interpolate(&model, alpha);
+        self.interpolate(model, alpha);
     }
 }
 
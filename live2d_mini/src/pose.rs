@@ -0,0 +1,296 @@
+use crate::model_resource::Live2DModelResource;
+use crate::pose_json;
+
+const EPSILON: f32 = 0.001;
+/// pose3.jsonにFadeInTimeがない場合の値[秒]
+const DEFAULT_FADE_IN_TIME: f32 = 0.5;
+/// 背景が出にくいように不透明度を調整するときの係数
+const PHI: f32 = 0.5;
+const BACK_OPACITY_THRESHOLD: f32 = 0.15;
+
+/// 切り替え対象のパーツ
+#[derive(Debug, Clone, PartialEq)]
+struct PoseParts {
+    /// パーツと同じidのパラメータの番号
+    parameter_index: Option<usize>,
+    part_index: Option<usize>,
+    /// 同じ不透明度にするパーツ
+    link: Vec<PoseParts>,
+}
+
+impl PoseParts {
+    fn new(id: &str, link: &[String], model: &Live2DModelResource) -> Self {
+        PoseParts {
+            parameter_index: model.parameter_index(id),
+            part_index: model.part_index(id),
+            link: link
+                .iter()
+                .map(|id| PoseParts::new(id, &[], model))
+                .collect(),
+        }
+    }
+
+    /// 表示するパーツか
+    fn is_visible(&self, parameter_values: &[f32], part_opacities: &[f32]) -> bool {
+        match (self.parameter_index, self.part_index) {
+            (Some(index), _) => parameter_values[index] > EPSILON,
+            (None, Some(index)) => part_opacities[index] > EPSILON,
+            (None, None) => false,
+        }
+    }
+
+    fn opacity(&self, part_opacities: &[f32]) -> f32 {
+        self.part_index
+            .map(|index| part_opacities[index])
+            .unwrap_or(0.0)
+    }
+
+    fn set_opacity(&self, part_opacities: &mut [f32], opacity: f32) {
+        if let Some(index) = self.part_index {
+            part_opacities[index] = opacity;
+        }
+    }
+}
+
+/// パーツの表示を切り替える
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Effect/CubismPose.cpp
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// 同時に一つしか表示しないパーツのグループ
+    groups: Vec<Vec<PoseParts>>,
+    /// フェードにかかる時間
+    fade_time: f32,
+}
+
+impl Pose {
+    pub fn new(json: &pose_json::PoseJson, model: &Live2DModelResource) -> Self {
+        let groups = json
+            .Groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|parts| PoseParts::new(&parts.Id, &parts.Link, model))
+                    .collect()
+            })
+            .collect();
+
+        Pose {
+            groups,
+            fade_time: json
+                .FadeInTime
+                .filter(|time| *time >= 0.0)
+                .unwrap_or(DEFAULT_FADE_IN_TIME),
+        }
+    }
+
+    /// 各グループの最初のパーツだけを表示する
+    pub fn reset(&self, model: &mut Live2DModelResource) {
+        for group in self.groups.iter() {
+            for (i, parts) in group.iter().enumerate() {
+                let value = if i == 0 { 1.0 } else { 0.0 };
+                if let Some(index) = parts.parameter_index {
+                    model.csm_get_mut_parameter_values()[index] = value;
+                }
+                parts.set_opacity(model.csm_get_mut_part_opacities(), value);
+            }
+        }
+        self.copy_part_opacities(model.csm_get_mut_part_opacities());
+    }
+
+    /// 表示するパーツをフェードインさせ、それ以外をフェードアウトさせる
+    pub fn update(&self, model: &mut Live2DModelResource, delta_time: f32) {
+        let delta_time = delta_time.max(0.0);
+
+        for group in self.groups.iter() {
+            let (visible_index, new_opacity) = self.visible_parts(
+                model.csm_get_parameter_values(),
+                model.csm_get_part_opacities(),
+                delta_time,
+                group,
+            );
+            fade_parts(
+                model.csm_get_mut_part_opacities(),
+                group,
+                visible_index,
+                new_opacity,
+            );
+        }
+        self.copy_part_opacities(model.csm_get_mut_part_opacities());
+    }
+
+    /// 表示するパーツの番号とフェードイン後の不透明度
+    /// 表示するパーツがなければ最初のパーツを表示する
+    fn visible_parts(
+        &self,
+        parameter_values: &[f32],
+        part_opacities: &[f32],
+        delta_time: f32,
+        group: &[PoseParts],
+    ) -> (usize, f32) {
+        let parts = group
+            .iter()
+            .enumerate()
+            .find(|(_, parts)| parts.is_visible(parameter_values, part_opacities));
+
+        match parts {
+            Some((i, _)) if self.fade_time == 0.0 => (i, 1.0),
+            Some((i, parts)) => (
+                i,
+                (parts.opacity(part_opacities) + delta_time / self.fade_time).min(1.0),
+            ),
+            None => (0, 1.0),
+        }
+    }
+
+    /// リンクしているパーツに親の不透明度をコピーする
+    fn copy_part_opacities(&self, part_opacities: &mut [f32]) {
+        for parts in self.groups.iter().flatten() {
+            if parts.link.is_empty() {
+                continue;
+            }
+
+            let opacity = parts.opacity(part_opacities);
+            for link in parts.link.iter() {
+                link.set_opacity(part_opacities, opacity);
+            }
+        }
+    }
+}
+
+/// 表示するパーツをnew_opacityにして、それ以外をフェードアウトさせる
+fn fade_parts(
+    part_opacities: &mut [f32],
+    group: &[PoseParts],
+    visible_index: usize,
+    new_opacity: f32,
+) {
+    for (i, parts) in group.iter().enumerate() {
+        if i == visible_index {
+            parts.set_opacity(part_opacities, new_opacity);
+            continue;
+        }
+
+        // 表示パーツに合わせて非表示パーツを消していく
+        let mut a1 = if new_opacity < PHI {
+            new_opacity * (PHI - 1.0) / PHI + 1.0
+        } else {
+            (1.0 - new_opacity) * PHI / (1.0 - PHI)
+        };
+
+        // 背景が見えすぎないように制限する
+        let back_opacity = (1.0 - a1) * (1.0 - new_opacity);
+        if back_opacity > BACK_OPACITY_THRESHOLD {
+            a1 = 1.0 - BACK_OPACITY_THRESHOLD / (1.0 - new_opacity);
+        }
+
+        let opacity = parts.opacity(part_opacities);
+        if opacity > a1 {
+            parts.set_opacity(part_opacities, a1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(index: usize, link: &[usize]) -> PoseParts {
+        PoseParts {
+            parameter_index: Some(index),
+            part_index: Some(index),
+            link: link
+                .iter()
+                .map(|index| PoseParts {
+                    parameter_index: None,
+                    part_index: Some(*index),
+                    link: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    fn assert_opacities(actual: &[f32], expected: &[f32]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "{:?} {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn fade_test() {
+        let pose = Pose {
+            groups: vec![vec![parts(0, &[]), parts(1, &[])]],
+            fade_time: 0.5,
+        };
+        let group = &pose.groups[0];
+        let parameter_values = [0.0, 1.0];
+        let mut part_opacities = [1.0, 0.0];
+
+        // 0.2 < Phi: a1 = 0.2 * (0.5 - 1) / 0.5 + 1 = 0.8
+        // 背景 (1 - 0.8) * (1 - 0.2) = 0.16 > 0.15 なので 1 - 0.15 / 0.8
+        let (visible_index, new_opacity) =
+            pose.visible_parts(&parameter_values, &part_opacities, 0.1, group);
+        assert_eq!(visible_index, 1);
+        fade_parts(&mut part_opacities, group, visible_index, new_opacity);
+        assert_opacities(&part_opacities, &[0.8125, 0.2]);
+
+        // 0.6 >= Phi: a1 = (1 - 0.6) * 0.5 / (1 - 0.5) = 0.4
+        // 背景 0.6 * 0.4 = 0.24 > 0.15 なので 1 - 0.15 / 0.4
+        let (visible_index, new_opacity) =
+            pose.visible_parts(&parameter_values, &part_opacities, 0.2, group);
+        fade_parts(&mut part_opacities, group, visible_index, new_opacity);
+        assert_opacities(&part_opacities, &[0.625, 0.6]);
+
+        // 0.9 >= Phi: a1 = 0.1 背景 0.9 * 0.1 = 0.09 は制限しない
+        let (visible_index, new_opacity) =
+            pose.visible_parts(&parameter_values, &part_opacities, 0.15, group);
+        fade_parts(&mut part_opacities, group, visible_index, new_opacity);
+        assert_opacities(&part_opacities, &[0.1, 0.9]);
+
+        let (visible_index, new_opacity) =
+            pose.visible_parts(&parameter_values, &part_opacities, 1.0, group);
+        fade_parts(&mut part_opacities, group, visible_index, new_opacity);
+        assert_opacities(&part_opacities, &[0.0, 1.0]);
+    }
+
+    #[test]
+    fn visible_parts_test() {
+        let mut pose = Pose {
+            groups: vec![vec![parts(0, &[2]), parts(1, &[3])]],
+            fade_time: 0.5,
+        };
+        let group = &pose.groups[0];
+
+        // 表示するパーツがなければ最初のパーツをすぐに表示する
+        assert_eq!(
+            pose.visible_parts(&[0.0, 0.0], &[0.0, 0.0], 0.1, group),
+            (0, 1.0)
+        );
+        // 複数ある場合は最初のパーツ
+        assert_eq!(
+            pose.visible_parts(&[1.0, 1.0], &[0.0, 0.5], 0.1, group),
+            (0, 0.2)
+        );
+
+        // 表示を切り替えるとリンクしているパーツも同じ不透明度になる
+        let mut part_opacities = [1.0, 0.0, 1.0, 0.0];
+        let (visible_index, new_opacity) =
+            pose.visible_parts(&[0.0, 1.0], &part_opacities, 0.5, group);
+        fade_parts(&mut part_opacities, group, visible_index, new_opacity);
+        pose.copy_part_opacities(&mut part_opacities);
+        assert_opacities(&part_opacities, &[0.0, 1.0, 0.0, 1.0]);
+
+        // フェード時間が0ならすぐに切り替える
+        pose.fade_time = 0.0;
+        let group = &pose.groups[0];
+        assert_eq!(
+            pose.visible_parts(&[1.0, 0.0], &part_opacities, 0.0, group),
+            (0, 1.0)
+        );
+    }
+}
@@ -5,7 +5,7 @@ use serde::*;
 #[derive(Debug, Deserialize)]
 pub struct PoseJson {
     pub Type: String,
-    pub FadeInTime: f32,
+    pub FadeInTime: Option<f32>,
     pub Groups: Vec<Vec<Group>>,
 }
 
@@ -13,5 +13,6 @@ pub struct PoseJson {
 
 pub struct Group {
     pub Id: String,
+    #[serde(default)]
     pub Link: Vec<String>,
 }
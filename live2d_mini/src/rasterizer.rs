@@ -0,0 +1,595 @@
+use image::{Rgba, RgbaImage};
+
+use crate::clipping::{ClippingContext, DEFAULT_MASK_SIZE, MASK_CHANNEL_COUNT};
+use crate::draw_list::{apply_transform, BlendMode, DrawCommand, DrawList, IDENTITY_TRANSFORM};
+use crate::matrix::Matrix44;
+use crate::model::Live2DModel;
+use crate::model_resource::Live2DCanvasInfo;
+
+/// モデルの座標から画像のピクセル座標への変換
+pub use crate::draw_list::Transform;
+
+/// GPUを使わずにモデルを画像に描画する
+#[derive(Debug, Clone)]
+pub struct Rasterizer {
+    width: u32,
+    height: u32,
+    transform: Transform,
+    background: [f32; 4],
+
+    /// 乗算済みアルファの描画先
+    color_buffer: Vec<[f32; 4]>,
+    /// マスクのアトラス チャンネルごとに別のマスクを描画する
+    mask_buffer: Vec<[f32; MASK_CHANNEL_COUNT]>,
+    mask_size: u32,
+    draw_list: DrawList,
+}
+
+impl Rasterizer {
+    /// width x heightの画像に描画する
+    /// 変換はキャンバスのサイズが分からないのでピクセル単位のまま
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixel_count = width as usize * height as usize;
+
+        Rasterizer {
+            width,
+            height,
+            transform: IDENTITY_TRANSFORM,
+            background: [0.0; 4],
+            color_buffer: vec![[0.0; 4]; pixel_count],
+            mask_buffer: vec![
+                [0.0; MASK_CHANNEL_COUNT];
+                (DEFAULT_MASK_SIZE * DEFAULT_MASK_SIZE) as usize
+            ],
+            mask_size: DEFAULT_MASK_SIZE,
+            draw_list: DrawList::new(),
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    /// キャンバス全体が画像に収まるように変換を設定する
+    pub fn fit_canvas(&mut self, canvas_info: &Live2DCanvasInfo) {
+        self.transform = fit_canvas(canvas_info, self.width, self.height);
+    }
+
+    /// モデルの座標からクリップ座標(-1.0から1.0)への行列で変換を設定する
+    /// MiniquadRendererに渡す行列と同じものを使える
+    pub fn set_projection(&mut self, projection: &Matrix44) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let mut viewport = Matrix44::scaling(width / 2.0, -height / 2.0);
+        viewport.translate(width / 2.0, height / 2.0);
+        self.transform = viewport.multiply(projection).to_transform();
+    }
+
+    #[inline]
+    pub fn mask_size(&self) -> u32 {
+        self.mask_size
+    }
+
+    /// マスクのアトラスの一辺のピクセル数
+    /// マスクが多い場合やマスクの境界がぼやける場合は大きくする
+    pub fn set_mask_size(&mut self, mask_size: u32) {
+        self.mask_size = mask_size.max(1);
+        self.mask_buffer =
+            vec![[0.0; MASK_CHANNEL_COUNT]; (self.mask_size * self.mask_size) as usize];
+    }
+
+    /// 背景色 初期値は透明
+    pub fn set_background(&mut self, background: Rgba<u8>) {
+        let [r, g, b, a] = background.0.map(|value| value as f32 / 255.0);
+        // 乗算済みアルファにする
+        self.background = [r * a, g * a, b * a, a];
+    }
+
+    /// モデルの現在の状態を描画する
+    /// キャンバス全体が画像に収まるように描画する
+    pub fn render_model(&mut self, model: &Live2DModel) -> RgbaImage {
+        self.fit_canvas(&model.resource.csm_read_canvas_info());
+
+        let mut draw_list = std::mem::take(&mut self.draw_list);
+        draw_list.build(&model.resource);
+        let image = self.render(&draw_list, &model.textures);
+        self.draw_list = draw_list;
+
+        image
+    }
+
+    /// 描画命令を順番に描画する
+    pub fn render(&mut self, draw_list: &DrawList, textures: &[RgbaImage]) -> RgbaImage {
+        self.color_buffer.fill(self.background);
+        self.render_masks(draw_list, textures);
+
+        for command in draw_list.commands.iter() {
+            let texture = match textures.get(command.texture_index) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            self.render_command(draw_list, command, texture);
+        }
+
+        self.to_image()
+    }
+
+    /// 全てのマスクをアトラスに描画する
+    /// 同じマスクの組み合わせは一度だけ描画する
+    fn render_masks(&mut self, draw_list: &DrawList, textures: &[RgbaImage]) {
+        self.mask_buffer.fill([0.0; MASK_CHANNEL_COUNT]);
+
+        for context in draw_list.clipping_contexts.iter() {
+            self.render_mask(draw_list, context, textures);
+        }
+    }
+
+    /// contextのマスクをアトラスのレイアウトの範囲に描画する
+    fn render_mask(
+        &mut self,
+        draw_list: &DrawList,
+        context: &ClippingContext,
+        textures: &[RgbaImage],
+    ) {
+        let size = self.mask_size;
+        let transform = context.transform.map(|value| value * size as f32);
+        let [x, y, width, height] = context.layout.map(|value| value * size as f32);
+        let (min_x, min_y) = (x as usize, y as usize);
+        let (max_x, max_y) = ((x + width) as usize, (y + height) as usize);
+
+        for mask_index in context.masks.iter() {
+            let texture = match draw_list
+                .meshes
+                .get(*mask_index)
+                .and_then(|mesh| textures.get(mesh.texture_index))
+            {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            let positions = draw_list.mesh_positions(*mask_index);
+            let uvs = draw_list.mesh_uvs(*mask_index);
+            let mask_buffer = &mut self.mask_buffer;
+
+            for triangle in draw_list.mesh_indices(*mask_index).chunks_exact(3) {
+                rasterize_triangle(
+                    positions,
+                    uvs,
+                    triangle,
+                    &transform,
+                    size,
+                    size,
+                    false,
+                    |pixel, uv, _| {
+                        // 他のレイアウトにはみ出さない
+                        let (x, y) = (pixel % size as usize, pixel / size as usize);
+                        if x < min_x || x >= max_x || y < min_y || y >= max_y {
+                            return;
+                        }
+
+                        // テクスチャのアルファを重ねる
+                        let alpha = sample(texture, uv)[3];
+                        let mask = &mut mask_buffer[pixel][context.channel];
+                        *mask = alpha + *mask * (1.0 - alpha);
+                    },
+                );
+            }
+        }
+    }
+
+    fn render_command(&mut self, draw_list: &DrawList, command: &DrawCommand, texture: &RgbaImage) {
+        let positions = draw_list.mesh_positions(command.drawable_index);
+        let uvs = draw_list.mesh_uvs(command.drawable_index);
+        let transform = self.transform;
+        let (width, height) = (self.width, self.height);
+        let color_buffer = &mut self.color_buffer;
+        let mask_buffer = &self.mask_buffer;
+        let mask_size = self.mask_size;
+        let context = draw_list.clipping_context(command);
+
+        let multiply = command.multiply_color;
+        let screen = command.screen_color;
+
+        for triangle in draw_list
+            .mesh_indices(command.drawable_index)
+            .chunks_exact(3)
+        {
+            rasterize_triangle(
+                positions,
+                uvs,
+                triangle,
+                &transform,
+                width,
+                height,
+                command.culling,
+                |pixel, uv, position| {
+                    let [r, g, b, a] = sample(texture, uv);
+
+                    // 乗算色とスクリーン色を適用する
+                    let (r, g, b) = (r * multiply.r, g * multiply.g, b * multiply.b);
+                    let (r, g, b) = (
+                        r + screen.r - r * screen.r,
+                        g + screen.g - g * screen.g,
+                        b + screen.b - b * screen.b,
+                    );
+
+                    let mut alpha = a * command.opacity;
+                    if let Some(context) = context {
+                        let atlas_position = apply_transform(&context.transform, position);
+                        let mask =
+                            sample_mask(mask_buffer, mask_size, context.channel, atlas_position);
+                        alpha *= if command.inverted_mask {
+                            1.0 - mask
+                        } else {
+                            mask
+                        };
+                    }
+
+                    let source = [r * alpha, g * alpha, b * alpha, alpha];
+                    blend(&mut color_buffer[pixel], source, command.blend_mode);
+                },
+            );
+        }
+    }
+
+    fn to_image(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width, self.height);
+
+        for (pixel, color) in image.pixels_mut().zip(self.color_buffer.iter()) {
+            let alpha = color[3].clamp(0.0, 1.0);
+            let straight = |value: f32| {
+                if alpha > 0.0 {
+                    (value / alpha).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            };
+
+            *pixel = Rgba([
+                to_u8(straight(color[0])),
+                to_u8(straight(color[1])),
+                to_u8(straight(color[2])),
+                to_u8(alpha),
+            ]);
+        }
+
+        image
+    }
+}
+
+/// キャンバス全体がwidth x heightに収まり中央に来る変換
+pub fn fit_canvas(canvas_info: &Live2DCanvasInfo, width: u32, height: u32) -> Transform {
+    let canvas_width = canvas_info.out_size_in_pixels.x();
+    let canvas_height = canvas_info.out_size_in_pixels.y();
+    if canvas_width <= 0.0 || canvas_height <= 0.0 {
+        return IDENTITY_TRANSFORM;
+    }
+
+    let scale = (width as f32 / canvas_width).min(height as f32 / canvas_height);
+    let offset_x = (width as f32 - canvas_width * scale) / 2.0;
+    let offset_y = (height as f32 - canvas_height * scale) / 2.0;
+    let pixels_per_unit = canvas_info.out_pixels_per_unit * scale;
+
+    // モデルの座標はy軸が上向き
+    [
+        pixels_per_unit,
+        0.0,
+        0.0,
+        -pixels_per_unit,
+        offset_x + canvas_info.out_origin_in_pixels.x() * scale,
+        offset_y + canvas_info.out_origin_in_pixels.y() * scale,
+    ]
+}
+
+/// 三角形に含まれるピクセルごとにfを呼ぶ
+/// fにはピクセルの番号と補間したuv座標・モデルの座標を渡す
+#[allow(clippy::too_many_arguments)]
+fn rasterize_triangle<F>(
+    positions: &[[f32; 2]],
+    uvs: &[[f32; 2]],
+    triangle: &[u16],
+    transform: &Transform,
+    width: u32,
+    height: u32,
+    culling: bool,
+    mut f: F,
+) where
+    F: FnMut(usize, [f32; 2], [f32; 2]),
+{
+    let vertex = |index: u16| -> Option<([f32; 2], [f32; 2], [f32; 2])> {
+        let index = index as usize;
+        let position = *positions.get(index)?;
+        let uv = *uvs.get(index)?;
+        Some((position, apply_transform(transform, position), uv))
+    };
+    let (Some(v0), Some(v1), Some(v2)) = (
+        vertex(triangle[0]),
+        vertex(triangle[1]),
+        vertex(triangle[2]),
+    ) else {
+        return;
+    };
+
+    // 裏面はモデルの座標で時計回り
+    if culling && edge(v0.0, v1.0, v2.0) < 0.0 {
+        return;
+    }
+
+    // ピクセル座標で面積が正になる順番にそろえる
+    let (v1, v2) = if edge(v0.1, v1.1, v2.1) < 0.0 {
+        (v2, v1)
+    } else {
+        (v1, v2)
+    };
+    let (p0, p1, p2) = (v0.1, v1.1, v2.1);
+    let area = edge(p0, p1, p2);
+    if area <= 0.0 || !area.is_finite() {
+        return;
+    }
+
+    let min_x = p0[0].min(p1[0]).min(p2[0]).floor().max(0.0) as u32;
+    let min_y = p0[1].min(p1[1]).min(p2[1]).floor().max(0.0) as u32;
+    let max_x = (p0[0].max(p1[0]).max(p2[0]).ceil().max(0.0) as u32).min(width);
+    let max_y = (p0[1].max(p1[1]).max(p2[1]).ceil().max(0.0) as u32).min(height);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let point = [x as f32 + 0.5, y as f32 + 0.5];
+            let w0 = edge(p1, p2, point);
+            let w1 = edge(p2, p0, point);
+            let w2 = edge(p0, p1, point);
+
+            // 隣り合う三角形で辺上のピクセルを二重に描画しない
+            if !inside(w0, p1, p2) || !inside(w1, p2, p0) || !inside(w2, p0, p1) {
+                continue;
+            }
+
+            let (w0, w1, w2) = (w0 / area, w1 / area, w2 / area);
+            let interpolate = |a: [f32; 2], b: [f32; 2], c: [f32; 2]| {
+                [
+                    a[0] * w0 + b[0] * w1 + c[0] * w2,
+                    a[1] * w0 + b[1] * w1 + c[1] * w2,
+                ]
+            };
+
+            f(
+                y as usize * width as usize + x as usize,
+                interpolate(v0.2, v1.2, v2.2),
+                interpolate(v0.0, v1.0, v2.0),
+            );
+        }
+    }
+}
+
+#[inline]
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// 辺上の点は辺の向きで片方の三角形だけに含める
+#[inline]
+fn inside(w: f32, a: [f32; 2], b: [f32; 2]) -> bool {
+    w > 0.0 || (w == 0.0 && (a[1] > b[1] || (a[1] == b[1] && a[0] > b[0])))
+}
+
+/// バイリニア補間でテクスチャの色を取得する
+/// テクスチャは上下反転して読み込んでいるのでvをそのまま行にする
+fn sample(texture: &RgbaImage, [u, v]: [f32; 2]) -> [f32; 4] {
+    let (width, height) = texture.dimensions();
+    if width == 0 || height == 0 {
+        return [0.0; 4];
+    }
+
+    let x = (u * width as f32 - 0.5).clamp(0.0, (width - 1) as f32);
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let texel = |x: u32, y: u32| texture.get_pixel(x, y).0.map(|value| value as f32 / 255.0);
+    let (c00, c10, c01, c11) = (texel(x0, y0), texel(x1, y0), texel(x0, y1), texel(x1, y1));
+
+    let mut color = [0.0; 4];
+    for i in 0..4 {
+        let top = c00[i] + (c10[i] - c00[i]) * tx;
+        let bottom = c01[i] + (c11[i] - c01[i]) * tx;
+        color[i] = top + (bottom - top) * ty;
+    }
+
+    color
+}
+
+/// アトラスのchannelの値をバイリニア補間で取得する
+fn sample_mask(
+    mask_buffer: &[[f32; MASK_CHANNEL_COUNT]],
+    size: u32,
+    channel: usize,
+    [u, v]: [f32; 2],
+) -> f32 {
+    let max = (size - 1) as f32;
+    let x = (u * size as f32 - 0.5).clamp(0.0, max);
+    let y = (v * size as f32 - 0.5).clamp(0.0, max);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = (
+        (x0 + 1).min(size as usize - 1),
+        (y0 + 1).min(size as usize - 1),
+    );
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let value = |x: usize, y: usize| mask_buffer[y * size as usize + x][channel];
+    let top = value(x0, y0) + (value(x1, y0) - value(x0, y0)) * tx;
+    let bottom = value(x0, y1) + (value(x1, y1) - value(x0, y1)) * tx;
+
+    top + (bottom - top) * ty
+}
+
+/// 乗算済みアルファで合成する
+/// Cubismのシェーダーと同じ合成方法
+fn blend(destination: &mut [f32; 4], source: [f32; 4], blend_mode: BlendMode) {
+    match blend_mode {
+        BlendMode::Normal => {
+            for i in 0..4 {
+                destination[i] = source[i] + destination[i] * (1.0 - source[3]);
+            }
+        }
+        // アルファは変えない
+        BlendMode::Additive => {
+            for i in 0..3 {
+                destination[i] = (source[i] + destination[i]).min(1.0);
+            }
+        }
+        BlendMode::Multiplicative => {
+            for i in 0..3 {
+                destination[i] = source[i] * destination[i] + destination[i] * (1.0 - source[3]);
+            }
+        }
+    }
+}
+
+#[inline]
+fn to_u8(value: f32) -> u8 {
+    (value * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw_list::DrawMesh;
+    use crate::model_resource::Live2DColor;
+
+    /// 画像全体を覆う四角形
+    fn quad_draw_list(commands: Vec<DrawCommand>) -> DrawList {
+        let mut draw_list = DrawList::new();
+        let quad = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        for _ in 0..2 {
+            let vertex_start = draw_list.positions.len();
+            let index_start = draw_list.indices.len();
+            draw_list.positions.extend_from_slice(&quad);
+            draw_list.uvs.extend_from_slice(&[[0.5, 0.5]; 4]);
+            draw_list.indices.extend_from_slice(&[0, 1, 2, 0, 2, 3]);
+            draw_list.meshes.push(DrawMesh {
+                texture_index: 0,
+                vertex_range: vertex_start..draw_list.positions.len(),
+                index_range: index_start..draw_list.indices.len(),
+            });
+        }
+        draw_list.commands = commands;
+        draw_list.update_clipping();
+        draw_list
+    }
+
+    #[test]
+    fn blend_test() {
+        let textures = [RgbaImage::from_pixel(1, 1, Rgba([200, 100, 50, 255]))];
+        let mut rasterizer = Rasterizer::new(4, 4);
+        rasterizer.set_background(Rgba([100, 100, 100, 255]));
+
+        let normal = DrawCommand {
+            opacity: 0.5,
+            ..Default::default()
+        };
+        let image = rasterizer.render(&quad_draw_list(vec![normal]), &textures);
+        assert_eq!(image.get_pixel(1, 1), &Rgba([150, 100, 75, 255]));
+
+        let additive = DrawCommand {
+            blend_mode: BlendMode::Additive,
+            ..Default::default()
+        };
+        let image = rasterizer.render(&quad_draw_list(vec![additive]), &textures);
+        assert_eq!(image.get_pixel(1, 1), &Rgba([255, 200, 150, 255]));
+
+        let multiplicative = DrawCommand {
+            blend_mode: BlendMode::Multiplicative,
+            ..Default::default()
+        };
+        let image = rasterizer.render(&quad_draw_list(vec![multiplicative]), &textures);
+        assert_eq!(image.get_pixel(1, 1), &Rgba([78, 39, 20, 255]));
+    }
+
+    #[test]
+    fn color_test() {
+        let textures = [RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]))];
+        let mut rasterizer = Rasterizer::new(4, 4);
+
+        let colored = DrawCommand {
+            multiply_color: Live2DColor::new(1.0, 0.0, 0.0, 1.0),
+            screen_color: Live2DColor::new(0.0, 0.0, 1.0, 1.0),
+            ..Default::default()
+        };
+        let image = rasterizer.render(&quad_draw_list(vec![colored]), &textures);
+        assert_eq!(image.get_pixel(2, 2), &Rgba([255, 0, 255, 255]));
+    }
+
+    #[test]
+    fn mask_test() {
+        let textures = [RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]))];
+        let mut rasterizer = Rasterizer::new(4, 4);
+
+        // 左半分だけのマスク
+        let mut draw_list = quad_draw_list(vec![DrawCommand {
+            drawable_index: 1,
+            masks: vec![0],
+            ..Default::default()
+        }]);
+        draw_list.positions[1] = [2.0, 0.0];
+        draw_list.positions[2] = [2.0, 4.0];
+        draw_list.update_clipping();
+        let image = rasterizer.render(&draw_list, &textures);
+        assert_eq!(image.get_pixel(0, 0)[3], 255);
+        assert_eq!(image.get_pixel(3, 0)[3], 0);
+
+        draw_list.commands[0].inverted_mask = true;
+        let image = rasterizer.render(&draw_list, &textures);
+        assert_eq!(image.get_pixel(0, 0)[3], 0);
+        assert_eq!(image.get_pixel(3, 0)[3], 255);
+    }
+
+    #[test]
+    fn projection_test() {
+        let mut rasterizer = Rasterizer::new(4, 2);
+        rasterizer.set_projection(&Matrix44::scaling(0.5, 0.5));
+
+        // クリップ座標の左上が画像の左上になる
+        assert_eq!(
+            apply_transform(rasterizer.transform(), [-2.0, 2.0]),
+            [0.0, 0.0]
+        );
+        assert_eq!(
+            apply_transform(rasterizer.transform(), [2.0, -2.0]),
+            [4.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn culling_test() {
+        let textures = [RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]))];
+        let mut rasterizer = Rasterizer::new(4, 4);
+
+        let culled = DrawCommand {
+            culling: true,
+            ..Default::default()
+        };
+        let mut draw_list = quad_draw_list(vec![culled]);
+        // 片方の三角形だけ裏返す
+        draw_list.indices[..3].copy_from_slice(&[0, 2, 1]);
+        let image = rasterizer.render(&draw_list, &textures);
+        // 時計回りの三角形(0, 2, 1)は右上
+        assert_eq!(image.get_pixel(3, 0)[3], 0);
+        assert_eq!(image.get_pixel(0, 3)[3], 255);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::model_resource::Live2DModelResource;
+
+/// 速度を計算するときの基準のフレームレート
+const FRAME_RATE: f32 = 30.0;
+const EPSILON: f32 = 0.01;
+/// 1秒間に動ける最大の距離
+const FACE_PARAM_MAX_VELOCITY: f32 = 40.0 / 10.0;
+/// 最大速度になるまでの時間[秒]
+const TIME_TO_MAX_SPEED: f32 = 0.15;
+
+/// 顔の向きを目標の位置に向かってなめらかに動かす
+/// 座標は-1.0から1.0に正規化したモデルの座標
+/// see: https://github.com/Live2D/CubismNativeFramework/blob/develop/src/Math/CubismTargetPoint.cpp
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TargetPoint {
+    face_target_x: f32,
+    face_target_y: f32,
+    face_x: f32,
+    face_y: f32,
+    face_vx: f32,
+    face_vy: f32,
+    last_time: f32,
+    user_time: f32,
+}
+
+impl TargetPoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 目標の位置を設定する
+    pub fn set(&mut self, x: f32, y: f32) {
+        self.face_target_x = x;
+        self.face_target_y = y;
+    }
+
+    #[inline]
+    pub fn x(&self) -> f32 {
+        self.face_x
+    }
+
+    #[inline]
+    pub fn y(&self) -> f32 {
+        self.face_y
+    }
+
+    /// 時間を進めて目標の位置に近づける
+    /// 加速度と速度を制限して急に動かないようにする
+    pub fn update(&mut self, delta_time: f32) {
+        self.user_time += delta_time;
+
+        let max_v = FACE_PARAM_MAX_VELOCITY / FRAME_RATE;
+
+        if self.last_time == 0.0 {
+            self.last_time = self.user_time;
+            return;
+        }
+
+        let delta_time_weight = (self.user_time - self.last_time) * FRAME_RATE;
+        self.last_time = self.user_time;
+
+        let frame_to_max_speed = TIME_TO_MAX_SPEED * FRAME_RATE;
+        let max_a = delta_time_weight * max_v / frame_to_max_speed;
+
+        let dx = self.face_target_x - self.face_x;
+        let dy = self.face_target_y - self.face_y;
+        if dx.abs() <= EPSILON && dy.abs() <= EPSILON {
+            return;
+        }
+
+        // 目標に向かう最大速度との差が加速度になる
+        let d = (dx * dx + dy * dy).sqrt();
+        let vx = max_v * dx / d;
+        let vy = max_v * dy / d;
+
+        let mut ax = vx - self.face_vx;
+        let mut ay = vy - self.face_vy;
+        let a = (ax * ax + ay * ay).sqrt();
+        if a < -max_a || a > max_a {
+            ax *= max_a / a;
+            ay *= max_a / a;
+        }
+
+        self.face_vx += ax;
+        self.face_vy += ay;
+
+        // 目標に近づいたら止まれるように減速する
+        let max_v = 0.5 * ((max_a * max_a + 16.0 * max_a * d - 8.0 * max_a * d).sqrt() - max_a);
+        let cur_v = (self.face_vx * self.face_vx + self.face_vy * self.face_vy).sqrt();
+        if cur_v > max_v {
+            self.face_vx *= max_v / cur_v;
+            self.face_vy *= max_v / cur_v;
+        }
+
+        self.face_x += self.face_vx;
+        self.face_y += self.face_vy;
+    }
+}
+
+/// 目標の位置からパラメータに加算する値の計算方法
+/// x * x_scale + y * y_scale + x * y * xy_scaleを加算する
+#[derive(Debug, Clone, PartialEq)]
+pub struct DragParameter {
+    pub id: String,
+    pub x_scale: f32,
+    pub y_scale: f32,
+    pub xy_scale: f32,
+}
+
+impl DragParameter {
+    pub fn new(id: &str, x_scale: f32, y_scale: f32, xy_scale: f32) -> Self {
+        DragParameter {
+            id: id.to_string(),
+            x_scale,
+            y_scale,
+            xy_scale,
+        }
+    }
+
+    /// 公式サンプルと同じ設定
+    pub fn defaults() -> Vec<DragParameter> {
+        vec![
+            DragParameter::new("ParamAngleX", 30.0, 0.0, 0.0),
+            DragParameter::new("ParamAngleY", 0.0, 30.0, 0.0),
+            DragParameter::new("ParamAngleZ", 0.0, 0.0, -30.0),
+            DragParameter::new("ParamBodyAngleX", 10.0, 0.0, 0.0),
+            DragParameter::new("ParamEyeBallX", 1.0, 0.0, 0.0),
+            DragParameter::new("ParamEyeBallY", 0.0, 1.0, 0.0),
+        ]
+    }
+
+    pub fn value(&self, x: f32, y: f32) -> f32 {
+        x * self.x_scale + y * self.y_scale + x * y * self.xy_scale
+    }
+}
+
+/// ドラッグやマウスカーソルの位置に顔・目・体を向ける
+#[derive(Debug, Clone, PartialEq)]
+pub struct DragController {
+    target_point: TargetPoint,
+    parameters: Vec<DragParameter>,
+    /// get_parameter_indexで取得したパラメータの番号
+    parameter_indices: Vec<usize>,
+}
+
+impl DragController {
+    pub fn new(parameters: Vec<DragParameter>, model: &mut Live2DModelResource) -> Self {
+        let parameter_indices = parameters
+            .iter()
+            .map(|parameter| model.get_parameter_index(&parameter.id))
+            .collect();
+
+        DragController {
+            target_point: TargetPoint::new(),
+            parameters,
+            parameter_indices,
+        }
+    }
+
+    /// 公式サンプルと同じ設定で生成する
+    pub fn with_default_parameters(model: &mut Live2DModelResource) -> Self {
+        Self::new(DragParameter::defaults(), model)
+    }
+
+    #[inline]
+    pub fn parameters(&self) -> &[DragParameter] {
+        &self.parameters
+    }
+
+    #[inline]
+    pub fn target_point(&self) -> &TargetPoint {
+        &self.target_point
+    }
+
+    /// 目標の位置を設定する
+    pub fn set_target(&mut self, x: f32, y: f32) {
+        self.target_point.set(x, y);
+    }
+
+    /// 時間を進めてパラメータに加算する
+    pub fn update(&mut self, model: &mut Live2DModelResource, delta_time: f32) {
+        self.target_point.update(delta_time);
+        let (x, y) = (self.target_point.x(), self.target_point.y());
+
+        let parameter_values = model.csm_get_mut_parameter_values();
+        for (parameter, index) in self.parameters.iter().zip(self.parameter_indices.iter()) {
+            // モデルに存在しないパラメータは無視する
+            if let Some(value) = parameter_values.get_mut(*index) {
+                *value += parameter.value(x, y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_point_test() {
+        let mut target_point = TargetPoint::new();
+        target_point.set(1.0, 0.0);
+
+        // 最初のupdateでは動かない
+        target_point.update(1.0 / 60.0);
+        assert_eq!(target_point.x(), 0.0);
+
+        let mut last_x = 0.0;
+        let mut last_v = 0.0;
+        for _ in 0..5 {
+            target_point.update(1.0 / 60.0);
+            let v = target_point.x() - last_x;
+            // 加速していく
+            assert!(v > last_v);
+            assert_eq!(target_point.y(), 0.0);
+            last_x = target_point.x();
+            last_v = v;
+        }
+
+        for _ in 0..600 {
+            target_point.update(1.0 / 60.0);
+            assert!(target_point.x() <= 1.0 + EPSILON);
+        }
+        assert!((target_point.x() - 1.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn drag_parameter_test() {
+        let parameter = DragParameter::new("ParamAngleZ", 1.0, 2.0, -30.0);
+        assert_eq!(parameter.value(0.5, 0.5), 0.5 + 1.0 - 7.5);
+    }
+}
@@ -1,6 +1,11 @@
 #[derive(Debug, Clone, Copy)]
 pub struct Live2DVector2(pub(crate) live2d_mini_sys::csmVector2);
 impl Live2DVector2 {
+    #[cfg(test)]
+    pub(crate) fn new(x: f32, y: f32) -> Self {
+        Live2DVector2(live2d_mini_sys::csmVector2 { X: x, Y: y })
+    }
+
     #[inline]
     pub fn x(&self) -> f32 {
         self.0.X
@@ -11,3 +16,9 @@ impl Live2DVector2 {
         self.0.Y
     }
 }
+
+impl PartialEq for Live2DVector2 {
+    fn eq(&self, other: &Self) -> bool {
+        self.x() == other.x() && self.y() == other.y()
+    }
+}
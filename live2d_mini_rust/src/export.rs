@@ -0,0 +1,208 @@
+use std::error::Error;
+use std::path::Path;
+
+use image::Rgba;
+use live2d_mini::export::{self, MotionFrames};
+use live2d_mini::model::Live2DModel;
+use live2d_mini::rasterizer::Rasterizer;
+
+pub const USAGE: &str = "usage: live2d_mini_rs export <model3.json|zip> <output> [options]
+    --size <WIDTHxHEIGHT>       image size (default 1024x1024)
+    --background <RRGGBB[AA]>   background color (default transparent)
+    --motion <GROUP:INDEX>      export a motion instead of a still image
+    --fps <FPS>                 frame rate of the motion (default 30)
+
+without --motion the current pose is saved as png.
+with --motion the output extension selects the format:
+    .gif        animated gif
+    .png .apng  animated png
+    otherwise   numbered png files in the directory";
+
+/// export サブコマンドの引数
+#[derive(Debug, PartialEq)]
+struct Options {
+    model: String,
+    output: String,
+    width: u32,
+    height: u32,
+    background: Rgba<u8>,
+    motion: Option<(String, usize)>,
+    fps: f32,
+}
+
+/// export サブコマンドを実行する
+/// argsはサブコマンド名より後の引数
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let options = parse_options(args)?;
+
+    let mut model = Live2DModel::load(&options.model)?;
+    let mut rasterizer = Rasterizer::new(options.width, options.height);
+    rasterizer.set_background(options.background);
+
+    let (group, index) = match &options.motion {
+        Some(motion) => motion,
+        None => {
+            model.update(0.0);
+            export::render_png(&model, &mut rasterizer, &options.output)?;
+            return Ok(());
+        }
+    };
+
+    let animation_index = model
+        .motion_index(group, *index)
+        .ok_or_else(|| format!("motion {}:{} is not found", group, index))?;
+    let frames = MotionFrames::new(&mut model, &mut rasterizer, animation_index, options.fps)?;
+    let fps = frames.fps();
+
+    let output = Path::new(&options.output);
+    let extension = output
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("gif") => export::save_gif(frames, fps, output)?,
+        Some("png") | Some("apng") => export::save_apng(frames, fps, output)?,
+        _ => {
+            export::save_png_sequence(frames, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut positional = vec![];
+    let mut width = 1024;
+    let mut height = 1024;
+    let mut background = Rgba([0, 0, 0, 0]);
+    let mut motion = None;
+    let mut fps = 30.0;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} needs a value\n{}", arg, USAGE))
+        };
+
+        match arg.as_str() {
+            "--size" => (width, height) = parse_size(value()?)?,
+            "--background" => background = parse_color(value()?)?,
+            "--motion" => motion = Some(parse_motion(value()?)?),
+            "--fps" => {
+                let value = value()?;
+                fps = value
+                    .parse()
+                    .map_err(|_| format!("invalid fps: {}", value))?;
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("unknown option {}\n{}", arg, USAGE));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let (model, output) = match <[String; 2]>::try_from(positional) {
+        Ok([model, output]) => (model, output),
+        Err(_) => return Err(USAGE.to_string()),
+    };
+
+    Ok(Options {
+        model,
+        output,
+        width,
+        height,
+        background,
+        motion,
+        fps,
+    })
+}
+
+/// 1280x720のような大きさ
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("invalid size: {}", value);
+    let (width, height) = value.split_once('x').ok_or_else(error)?;
+    let width = width.parse().map_err(|_| error())?;
+    let height = height.parse().map_err(|_| error())?;
+    if width == 0 || height == 0 {
+        return Err(error());
+    }
+
+    Ok((width, height))
+}
+
+/// ffffffやffffff80のような色 先頭の#はあってもよい
+fn parse_color(value: &str) -> Result<Rgba<u8>, String> {
+    let error = || format!("invalid color: {}", value);
+    let hex = value.trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(error());
+    }
+
+    let mut color = [0, 0, 0, 255];
+    for (channel, start) in color.iter_mut().zip((0..hex.len()).step_by(2)) {
+        *channel = u8::from_str_radix(&hex[start..start + 2], 16).map_err(|_| error())?;
+    }
+
+    Ok(Rgba(color))
+}
+
+/// Idle:0のようなモーショングループ名と番号
+fn parse_motion(value: &str) -> Result<(String, usize), String> {
+    let error = || format!("invalid motion: {}", value);
+    let (group, index) = value.rsplit_once(':').ok_or_else(error)?;
+    let index = index.parse().map_err(|_| error())?;
+
+    Ok((group.to_string(), index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_options_test() {
+        let options = parse_options(&args(&[
+            "Hiyori.model3.json",
+            "out.gif",
+            "--size",
+            "640x480",
+            "--background",
+            "#ff800040",
+            "--motion",
+            "Tap:1",
+            "--fps",
+            "24",
+        ]))
+        .unwrap();
+        assert_eq!(options.model, "Hiyori.model3.json");
+        assert_eq!(options.output, "out.gif");
+        assert_eq!((options.width, options.height), (640, 480));
+        assert_eq!(options.background, Rgba([255, 128, 0, 64]));
+        assert_eq!(options.motion, Some(("Tap".to_string(), 1)));
+        assert_eq!(options.fps, 24.0);
+
+        let options = parse_options(&args(&["model.zip", "out.png"])).unwrap();
+        assert_eq!((options.width, options.height), (1024, 1024));
+        assert_eq!(options.background, Rgba([0, 0, 0, 0]));
+        assert_eq!(options.motion, None);
+
+        assert!(parse_options(&args(&["model.zip"])).is_err());
+        assert!(parse_options(&args(&["a", "b", "--size"])).is_err());
+        assert!(parse_options(&args(&["a", "b", "--unknown", "1"])).is_err());
+    }
+
+    #[test]
+    fn parse_value_test() {
+        assert_eq!(parse_color("000000"), Ok(Rgba([0, 0, 0, 255])));
+        assert!(parse_color("fff").is_err());
+        assert!(parse_color("gggggg").is_err());
+        assert!(parse_size("0x10").is_err());
+        assert!(parse_size("10").is_err());
+        assert!(parse_motion("Idle").is_err());
+    }
+}